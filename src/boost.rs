@@ -0,0 +1,45 @@
+use time::Duration;
+use ::*;
+
+pub const BOOST_KEY: VirtualKeyCode = VirtualKeyCode::LShift;
+pub const BOOST_METER_MS: i64 = 3000;
+pub const BOOST_RECHARGE_RATE: i32 = 2; // Recharging takes this many times longer than draining
+pub const BOOST_COST_INTERVAL_MS: i64 = 500;
+pub const BOOST_METER_WIDTH: i32 = 6;
+
+// Holding the boost key halves the move frequency while the meter lasts. Every interval of boosting
+// costs a point of score, or a tail segment once the score is spent.
+pub fn update_boost(input_man: &InputMan, game: &mut Game, delta_time: Duration) {
+    let wants_boost: bool = input::is_key_held(input_man, BOOST_KEY) && (game.snake.direction != Direction::None);
+    game.is_boosting = wants_boost && (game.boost_meter > time::Duration::zero());
+
+    if !game.is_boosting {
+        let max_meter: Duration = time::Duration::milliseconds(BOOST_METER_MS);
+        game.boost_meter = max_meter.min(game.boost_meter + (delta_time / BOOST_RECHARGE_RATE));
+        return;
+    }
+
+    game.boost_meter = time::Duration::zero().max(game.boost_meter - delta_time);
+    game.boost_cost_timer = game.boost_cost_timer + delta_time;
+
+    if game.boost_cost_timer >= time::Duration::milliseconds(BOOST_COST_INTERVAL_MS) {
+        game.boost_cost_timer = time::Duration::zero();
+
+        if game.score > 0 {
+            game.score -= 1;
+        } else {
+            shrink_snake(&mut game.snake, 1);
+        }
+    }
+}
+
+pub fn render_boost_meter(renderer: &mut Renderer, game: &Game, x: i32, y: i32) {
+    let fill: f32 = game.boost_meter.num_milliseconds() as f32 / BOOST_METER_MS as f32;
+    let filled_cells: i32 = (fill * BOOST_METER_WIDTH as f32).ceil() as i32;
+    let color: Color = if game.is_boosting { COLOR_ORANGE } else { COLOR_WHITE };
+
+    for i in 0..BOOST_METER_WIDTH {
+        let graphic: char = if i < filled_cells { '█' } else { '░' };
+        gfx::draw_cell(renderer, x + i, y, Sprite::new(graphic, color));
+    }
+}
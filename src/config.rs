@@ -0,0 +1,45 @@
+use std::fs::File;
+use std::io::Read;
+
+pub fn read_file(path: &str) -> Result<String, String> {
+    let mut contents: String = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(|e| e.to_string())?;
+
+    Ok(contents)
+}
+
+// Splits the "key = value" lines that fonts and palettes are written in. Blank lines and lines starting
+// with '#' are skipped.
+pub fn parse_key_values(contents: &str) -> Result<Vec<(&str, &str)>, String> {
+    let mut pairs: Vec<(&str, &str)> = Vec::new();
+
+    for line in contents.lines() {
+        let line: &str = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.splitn(2, '=');
+        let key: &str = parts.next().unwrap_or("").trim();
+        match parts.next() {
+            Some(value) => { pairs.push((key, value.trim())); },
+            None => { return Err(format!("Expected \"key = value\" but found \"{}\"", line)); }
+        }
+    }
+
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_key_values_skips_comments_and_blank_lines() {
+        let pairs: Vec<(&str, &str)> = parse_key_values("# comment\n\nname = Amber = 2\n  food=FFB000  \n").unwrap();
+        assert_eq!(pairs, vec![("name", "Amber = 2"), ("food", "FFB000")]);
+        assert!(parse_key_values("food FFB000").is_err());
+    }
+}
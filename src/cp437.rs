@@ -0,0 +1,56 @@
+// Glyphs that have no code page 437 equivalent are drawn with this one
+pub const FALLBACK_GLYPH: u8 = b'?';
+
+// The Unicode character drawn by each code page 437 glyph, in atlas order
+pub const CP437_CHARS: [char; 256] = [
+    '\u{0}', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?',
+    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '\\', ']', '^', '_',
+    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~', '⌂',
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}'
+];
+
+// Finds the atlas glyph for a character. Printable ASCII maps to itself.
+pub fn to_cp437(c: char) -> Option<u8> {
+    if (c >= ' ') && (c <= '~') {
+        return Some(c as u8);
+    }
+
+    CP437_CHARS.iter().position(|&glyph| glyph == c).map(|index| index as u8)
+}
+
+pub fn to_cp437_or_fallback(c: char) -> u8 {
+    to_cp437(c).unwrap_or(FALLBACK_GLYPH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_glyph_maps_back_to_itself() {
+        for (index, &c) in CP437_CHARS.iter().enumerate() {
+            assert_eq!(to_cp437(c), Some(index as u8), "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn unmapped_characters_fall_back() {
+        assert_eq!(to_cp437('€'), None);
+        assert_eq!(to_cp437('あ'), None);
+        assert_eq!(to_cp437_or_fallback('€'), FALLBACK_GLYPH);
+        assert_eq!(to_cp437_or_fallback('█'), 219);
+        assert_eq!(to_cp437_or_fallback('A'), b'A');
+    }
+}
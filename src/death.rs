@@ -0,0 +1,55 @@
+use time::Duration;
+use ::*;
+
+pub const DEATH_FLASH_MS: i64 = 900;
+pub const DEATH_FLASH_INTERVAL_MS: i64 = 150;
+pub const DISSOLVE_STEP_MS: i64 = 60;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum DeathCause {
+    HitWall,
+    BitSelf,
+    HitObstacle,
+    TimeUp
+}
+
+pub struct Death {
+    pub cause: DeathCause,
+    pub position: Coordinates,
+    pub timer: Duration
+}
+
+impl Death {
+    pub fn new(cause: DeathCause, position: Coordinates) -> Death {
+        Death {
+            cause,
+            position,
+            timer: time::Duration::zero()
+        }
+    }
+
+    // The snake and the collision cell blink red before the body starts dissolving
+    pub fn is_flash_on(&self) -> bool {
+        let elapsed_ms: i64 = self.timer.num_milliseconds();
+        (elapsed_ms >= DEATH_FLASH_MS) || ((elapsed_ms / DEATH_FLASH_INTERVAL_MS) % 2 == 0)
+    }
+
+    // Number of segments that have dissolved so far, starting from the tail
+    pub fn dissolved_segments(&self) -> usize {
+        let elapsed_ms: i64 = self.timer.num_milliseconds() - DEATH_FLASH_MS;
+        if elapsed_ms <= 0 {
+            return 0;
+        }
+
+        (elapsed_ms / DISSOLVE_STEP_MS) as usize + 1
+    }
+}
+
+pub fn death_message(cause: DeathCause) -> &'static str {
+    match cause {
+        DeathCause::HitWall => "You hit a wall!",
+        DeathCause::BitSelf => "You bit yourself!",
+        DeathCause::HitObstacle => "You hit an obstacle!",
+        DeathCause::TimeUp => "You ran out of time!"
+    }
+}
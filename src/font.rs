@@ -0,0 +1,109 @@
+use image;
+use config;
+use image::RgbaImage;
+
+pub const FONTS_PATH: &'static str = "fonts";
+pub const FONT_FILES: [&'static str; 2] = [
+    "cp437_8x16.font",
+    "cp437_16x32.font"
+];
+
+// Describes a bitmap font atlas: glyphs are laid out in a grid, left to right and top to bottom
+#[derive(Clone)]
+pub struct Font {
+    pub name: String,
+    pub image_path: String,
+    pub glyph_width: u32,
+    pub glyph_height: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub spacing: u32, // Pixels between neighbouring glyphs in the atlas
+    pub atlas: RgbaImage // Loaded along with the description, so a missing image can't break a font switch
+}
+
+impl Font {
+    pub fn cp437() -> Font {
+        Font {
+            name: String::from("CP437 8x16"),
+            image_path: String::from("font.png"),
+            glyph_width: 8,
+            glyph_height: 16,
+            columns: 16,
+            rows: 16,
+            spacing: 0,
+            atlas: RgbaImage::new(0, 0)
+        }
+    }
+}
+
+// Fonts that fail to load are skipped. Without any, the game uses its own atlas.
+pub fn load_fonts() -> Vec<Font> {
+    let mut fonts: Vec<Font> = Vec::new();
+
+    for file_name in FONT_FILES.iter() {
+        let path: String = format!("{}/{}", FONTS_PATH, file_name);
+        match load_font(&path) {
+            Ok(font) => { fonts.push(font); },
+            Err(e) => { println!("Failed to load font {}: {}", path, e); }
+        }
+    }
+
+    if fonts.is_empty() {
+        let mut font: Font = Font::cp437();
+        if let Err(e) = load_atlas(&mut font) {
+            println!("Failed to load the built in font: {}", e);
+        }
+
+        fonts.push(font);
+    }
+
+    fonts
+}
+
+// Anything a font file doesn't set is taken from the atlas that ships with the game
+fn load_font(path: &str) -> Result<Font, String> {
+    let contents: String = config::read_file(path)?;
+    let mut font: Font = Font::cp437();
+
+    for (key, value) in config::parse_key_values(&contents)? {
+        match key {
+            "name" => { font.name = String::from(value); },
+            "image" => { font.image_path = String::from(value); },
+            "glyph_width" => { font.glyph_width = parse_size(value)?; },
+            "glyph_height" => { font.glyph_height = parse_size(value)?; },
+            "columns" => { font.columns = parse_size(value)?; },
+            "rows" => { font.rows = parse_size(value)?; },
+            "spacing" => { font.spacing = value.parse().map_err(|_| format!("Invalid spacing \"{}\"", value))?; },
+            _ => { return Err(format!("Unknown key \"{}\"", key)); }
+        }
+    }
+
+    load_atlas(&mut font)?;
+    Ok(font)
+}
+
+fn load_atlas(font: &mut Font) -> Result<(), String> {
+    let image = image::open(&font.image_path).map_err(|e| format!("{}: {}", font.image_path, e))?;
+    font.atlas = image.to_rgba();
+    check_atlas(font)
+}
+
+// Every glyph the description promises has to be inside the image
+pub fn check_atlas(font: &Font) -> Result<(), String> {
+    let width: u32 = (font.columns * (font.glyph_width + font.spacing)) - font.spacing;
+    let height: u32 = (font.rows * (font.glyph_height + font.spacing)) - font.spacing;
+
+    if (font.atlas.width() < width) || (font.atlas.height() < height) {
+        return Err(format!("{} is {}x{} but the glyphs need {}x{}", font.image_path,
+                           font.atlas.width(), font.atlas.height(), width, height));
+    }
+
+    Ok(())
+}
+
+fn parse_size(value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(format!("Invalid size \"{}\"", value))
+    }
+}
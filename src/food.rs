@@ -0,0 +1,91 @@
+use time::Duration;
+use ::*;
+
+pub const MAX_FOOD_COUNT: usize = 4;
+pub const POISON_SHRINK_SEGMENTS: usize = 2;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum FoodKind {
+    Normal,
+    Bonus,
+    Poison,
+    Speed
+}
+
+// Relative spawn weights
+pub const FOOD_WEIGHTS: [(FoodKind, u32); 4] = [
+    (FoodKind::Normal, 70),
+    (FoodKind::Bonus, 12),
+    (FoodKind::Poison, 10),
+    (FoodKind::Speed, 8)
+];
+
+#[derive(Clone)]
+pub struct Food {
+    pub kind: FoodKind,
+    pub position: Coordinates,
+    pub lifetime: Option<Duration>
+}
+
+impl Food {
+    pub fn new(kind: FoodKind, position: Coordinates) -> Food {
+        Food {
+            kind,
+            position,
+            lifetime: food_lifetime(kind)
+        }
+    }
+}
+
+// Normal food stays on the board until it is eaten
+pub fn food_lifetime(kind: FoodKind) -> Option<Duration> {
+    match kind {
+        FoodKind::Normal => None,
+        FoodKind::Bonus => Some(time::Duration::seconds(5)),
+        FoodKind::Poison => Some(time::Duration::seconds(10)),
+        FoodKind::Speed => Some(time::Duration::seconds(7))
+    }
+}
+
+pub fn food_value(kind: FoodKind) -> u32 {
+    match kind {
+        FoodKind::Normal => 1,
+        FoodKind::Bonus => 5,
+        FoodKind::Poison => 0,
+        FoodKind::Speed => 1
+    }
+}
+
+// Segments gained per food, before the level's growth multiplier
+pub fn food_growth(kind: FoodKind) -> u32 {
+    match kind {
+        FoodKind::Normal => 1,
+        FoodKind::Bonus => 3,
+        FoodKind::Poison => 0,
+        FoodKind::Speed => 1
+    }
+}
+
+pub fn food_sprite(kind: FoodKind) -> Sprite {
+    match kind {
+        FoodKind::Normal => SPRITE_FOOD,
+        FoodKind::Bonus => SPRITE_FOOD_BONUS,
+        FoodKind::Poison => SPRITE_FOOD_POISON,
+        FoodKind::Speed => SPRITE_FOOD_SPEED
+    }
+}
+
+pub fn random_food_kind<R: Rng>(rng: &mut R) -> FoodKind {
+    let total_weight: u32 = FOOD_WEIGHTS.iter().map(|&(_, weight)| weight).sum();
+    let mut roll: u32 = rng.gen_range(0, total_weight);
+
+    for &(kind, weight) in FOOD_WEIGHTS.iter() {
+        if roll < weight {
+            return kind;
+        }
+
+        roll -= weight;
+    }
+
+    FoodKind::Normal
+}
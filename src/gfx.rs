@@ -12,6 +12,14 @@ use glutin::GlContext;
 use gl::types::*;
 use image::RgbaImage;
 use nalgebra::*;
+use font::Font;
+use cp437;
+use software;
+use software::SoftwareBackend;
+#[cfg(unix)]
+use terminal;
+#[cfg(unix)]
+use terminal::TerminalBackend;
 
 const VS_SRC: &'static [u8] = b"
 #version 150 core
@@ -19,10 +27,10 @@ const VS_SRC: &'static [u8] = b"
 uniform mat4 modelViewProjection;
 
 in vec2 position;
-in vec3 color;
+in vec4 color;
 in vec2 uv;
 
-out vec3 Color;
+out vec4 Color;
 out vec2 TexCoord;
 
 void main()
@@ -38,35 +46,41 @@ const FS_SRC: &'static [u8] = b"
 
 uniform sampler2D tex;
 
-in vec3 Color;
+in vec4 Color;
 in vec2 TexCoord;
 
 out vec4 outColor;
 
 void main()
 {
-    outColor = texture(tex, TexCoord) * vec4(Color, 1.0);
+    outColor = texture(tex, TexCoord) * Color;
 }
 \0";
 
-pub const CELL_WIDTH: u32 = 8;
-pub const CELL_HEIGHT: u32 = 16;
+// Position (2), color (4) and uv (2)
+const VERTEX_SIZE: usize = 8;
+const QUAD_SIZE: usize = VERTEX_SIZE * 4;
+
+// Every cell of every layer owns a background quad and a glyph quad in the vertex buffer
+const CELL_SIZE: usize = QUAD_SIZE * 2;
+
+// Fills the window around the grid when it doesn't divide evenly
+const LETTERBOX_COLOR: Color = COLOR_BLACK;
 
 pub struct Window {
     pub events_loop: glutin::EventsLoop,
     pub gl_window: glutin::GlWindow,
     pub width: u32,
     pub height: u32,
-    pub is_close_requested: bool
+    pub is_close_requested: bool,
+    pub is_resized: bool // Set until the renderer has caught up with the new size
 }
 
 impl Window {
     pub fn new(title: &str, width: u32, height: u32) -> Window {
         let window = glutin::WindowBuilder::new()
             .with_title(title)
-            .with_dimensions(width, height)
-            .with_min_dimensions(width, height)
-            .with_max_dimensions(width, height);
+            .with_dimensions(width, height);
 
         let context = glutin::ContextBuilder::new()
             .with_vsync(true);
@@ -83,7 +97,8 @@ impl Window {
             gl_window,
             width,
             height,
-            is_close_requested: false
+            is_close_requested: false,
+            is_resized: false
         }
     }
 }
@@ -92,33 +107,175 @@ pub fn resize_window(window: &mut Window, width: u32, height: u32) {
     window.gl_window.resize(width, height);
     window.width = width;
     window.height = height;
+    window.is_resized = true;
+}
+
+// Asks the window system for a new size, e.g. after the cell size changed
+pub fn set_window_size(window: &mut Window, width: u32, height: u32) {
+    window.gl_window.set_inner_size(width, height);
+    resize_window(window, width, height);
+}
+
+// Layers are composited in this order, so later layers are drawn on top
+#[derive(Copy, Clone, PartialEq)]
+pub enum Layer {
+    Background,
+    World,
+    Effects,
+    Ui,
+    Modal
+}
+
+pub const LAYERS: [Layer; 5] = [Layer::Background, Layer::World, Layer::Effects, Layer::Ui, Layer::Modal];
+
+pub struct CellLayer {
+    cells: Vec<Sprite>,
+    uploaded_cells: Vec<Sprite> // What the GPU currently has, used to tell whether the layer changed
+}
+
+impl CellLayer {
+    pub fn new(cols: u32, rows: u32) -> CellLayer {
+        let mut cells: Vec<Sprite> = Vec::new();
+        cells.resize((cols * rows) as usize, SPRITE_NONE);
+
+        CellLayer {
+            uploaded_cells: cells.clone(),
+            cells
+        }
+    }
+
+    pub fn cells(&self) -> &[Sprite] {
+        &self.cells
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.cells != self.uploaded_cells
+    }
+}
+
+// Counts what was sent to the GPU, to compare against rebuilding a mesh of the visible quads every frame
+#[derive(Copy, Clone)]
+pub struct UploadStats {
+    pub frames: u64,
+    pub cells_uploaded: u64,
+    pub bytes_uploaded: u64,
+    pub buffer_calls: u64,
+    pub rebuild_bytes: u64 // What the rebuilt mesh would have sent instead
+}
+
+// Where the cell grid ends up. Drawing functions only touch the grid, so they work with any backend.
+pub enum Backend {
+    OpenGl(GlBackend),
+    Software(SoftwareBackend),
+    #[cfg(unix)]
+    Terminal(TerminalBackend)
 }
 
 pub struct Renderer {
     pub cols: u32,
     pub rows: u32,
-    cells: Vec<Sprite>,
-    needs_rebuild: bool,
+    pub text_color: Color,
+    pub border_color: Color,
+    pub background_color: Color,
+    pub layer: Layer, // Layer that drawing functions write to
+    pub font: Font,
+    pub cell_width: u32,
+    pub cell_height: u32,
+    pub backend: Backend,
+    layers: Vec<CellLayer>
+}
+
+pub struct GlBackend {
+    pub upload_stats: UploadStats,
+    needs_rebuild: bool, // Set when the cell size changes and every vertex has to move
+    shader_program: GLuint,
+    texture_id: GLuint,
+    atlas_width: u32,
+    atlas_height: u32,
     vao_id: GLuint,
     vbo_id: GLuint,
     ebo_id: GLuint,
     vertex_data: Vec<f32>,
-    element_data: Vec<u32>
+    element_data: Vec<u32>,
+    viewport: Viewport
+}
+
+// Where the grid ends up in the window. It is scaled by a whole number so pixels stay square and sharp,
+// and centered with bars around it. In a window too small for the grid it hangs off the edges.
+#[derive(Copy, Clone)]
+struct Viewport {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    scale: u32,
+    window_width: u32,
+    window_height: u32
 }
 
 impl Renderer {
-    pub fn new(window: &Window) -> Renderer {
-        gl::load_with(|symbol| window.gl_window.get_proc_address(symbol) as *const _);
+    pub fn new(window: &Window, font: Font) -> Renderer {
+        let cols: u32 = window.width / font.glyph_width;
+        let rows: u32 = window.height / font.glyph_height;
+        let gl_backend: GlBackend = GlBackend::new(window, &font, cols, rows);
 
-        let cols: u32 = window.width / CELL_WIDTH;
-        let rows: u32 = window.height / CELL_HEIGHT;
+        Renderer::with_backend(cols, rows, font, Backend::OpenGl(gl_backend))
+    }
 
-        let mut cells: Vec<Sprite> = Vec::new();
-        cells.resize((cols * rows) as usize, SPRITE_NONE);
+    // Renders into an image in memory, without a window or GL context
+    pub fn new_software(cols: u32, rows: u32, font: Font) -> Renderer {
+        let software_backend: SoftwareBackend = SoftwareBackend::new(&font, cols, rows);
+
+        Renderer::with_backend(cols, rows, font, Backend::Software(software_backend))
+    }
+
+    // Draws straight to the terminal. The font is only used to rasterize screenshots.
+    #[cfg(unix)]
+    pub fn new_terminal(cols: u32, rows: u32, font: Font) -> Renderer {
+        Renderer::with_backend(cols, rows, font, Backend::Terminal(TerminalBackend::new()))
+    }
+
+    fn with_backend(cols: u32, rows: u32, font: Font, backend: Backend) -> Renderer {
+        Renderer {
+            cols,
+            rows,
+            text_color: COLOR_WHITE,
+            border_color: COLOR_WHITE,
+            background_color: COLOR_BLACK,
+            layer: Layer::World,
+            cell_width: font.glyph_width,
+            cell_height: font.glyph_height,
+            font,
+            backend,
+            layers: LAYERS.iter().map(|_| CellLayer::new(cols, rows)).collect()
+        }
+    }
+
+    pub fn clear_cells(&mut self) {
+        for layer in LAYERS.iter() {
+            self.clear_layer(*layer);
+        }
+    }
+
+    pub fn clear_layer(&mut self, layer: Layer) {
+        for cell in self.layers[layer as usize].cells.iter_mut() {
+            *cell = SPRITE_NONE;
+        }
+    }
+
+    fn current_cells(&mut self) -> &mut Vec<Sprite> {
+        &mut self.layers[self.layer as usize].cells
+    }
+}
+
+impl GlBackend {
+    pub fn new(window: &Window, font: &Font, cols: u32, rows: u32) -> GlBackend {
+        gl::load_with(|symbol| window.gl_window.get_proc_address(symbol) as *const _);
 
         let mut vao: GLuint = 0;
         let mut vbo: GLuint = 0;
         let mut ebo: GLuint = 0;
+        let shader_program: GLuint;
 
         unsafe {
             gl::Enable(gl::BLEND);
@@ -137,7 +294,7 @@ impl Renderer {
             let vertex_shader: GLuint = compile_shader(gl::VERTEX_SHADER, VS_SRC);
             let fragment_shader: GLuint = compile_shader(gl::FRAGMENT_SHADER, FS_SRC);
 
-            let shader_program: GLuint = gl::CreateProgram();
+            shader_program = gl::CreateProgram();
             gl::AttachShader(shader_program, vertex_shader);
             gl::AttachShader(shader_program, fragment_shader);
             gl::LinkProgram(shader_program);
@@ -145,59 +302,22 @@ impl Renderer {
 
             let position_attribute: GLint = gl::GetAttribLocation(shader_program, b"position\0".as_ptr() as *const _);
             gl::VertexAttribPointer(position_attribute as GLuint, 2, gl::FLOAT, 0,
-                                    7 * mem::size_of::<f32>() as GLsizei,
+                                    (VERTEX_SIZE * mem::size_of::<f32>()) as GLsizei,
                                     ptr::null());
 
             let color_attribute: GLint = gl::GetAttribLocation(shader_program, b"color\0".as_ptr() as *const _);
-            gl::VertexAttribPointer(color_attribute as GLuint, 3, gl::FLOAT, 0,
-                                    7 * mem::size_of::<f32>() as GLsizei,
+            gl::VertexAttribPointer(color_attribute as GLuint, 4, gl::FLOAT, 0,
+                                    (VERTEX_SIZE * mem::size_of::<f32>()) as GLsizei,
                                     (2 * mem::size_of::<f32>()) as *const _);
 
             let uv_attribute: GLint = gl::GetAttribLocation(shader_program, b"uv\0".as_ptr() as *const _);
             gl::VertexAttribPointer(uv_attribute as GLuint, 2, gl::FLOAT, 0,
-                                    7 * mem::size_of::<f32>() as GLsizei,
-                                    (5 * mem::size_of::<f32>()) as *const _);
+                                    (VERTEX_SIZE * mem::size_of::<f32>()) as GLsizei,
+                                    (6 * mem::size_of::<f32>()) as *const _);
 
             gl::EnableVertexAttribArray(position_attribute as GLuint);
             gl::EnableVertexAttribArray(color_attribute as GLuint);
             gl::EnableVertexAttribArray(uv_attribute as GLuint);
-
-            let image: RgbaImage = image::open("font.png")
-                .expect("Failed to open image!")
-                .to_rgba();
-
-            let width: GLint = image.width() as GLint;
-            let height: GLint = image.height() as GLint;
-            let pixels: Vec<u8> = image.into_raw();
-
-            let mut texture_id: GLuint = 0;
-            gl::GenTextures(1, &mut texture_id);
-            gl::BindTexture(gl::TEXTURE_2D, texture_id);
-            gl::TexImage2D(gl::TEXTURE_2D,
-                            0,
-                            gl::RGBA8 as GLint,
-                            width,
-                            height,
-                            0,
-                            gl::RGBA,
-                            gl::UNSIGNED_BYTE,
-                            pixels.as_ptr() as *const _);
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-            gl::GenerateMipmap(gl::TEXTURE_2D);
-
-            let translation: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
-
-            let model: Matrix4<f32> = Isometry3::new(translation, nalgebra::zero()).to_homogeneous();
-            let view: Matrix4<f32> = Isometry3::new(Vector3::new(0.0, 0.0, -1.0), nalgebra::zero()).to_homogeneous();
-            let projection: Matrix4<f32> = Orthographic3::new(0.0, window.width as f32, 0.0, window.height as f32, 0.1, 1000.0).unwrap();
-            let model_view_projection = projection * model * view;
-
-            let uni_model_view_projection = gl::GetUniformLocation(shader_program, b"modelViewProjection\0".as_ptr() as *const _);
-            gl::UniformMatrix4fv(uni_model_view_projection, 1, gl::FALSE, model_view_projection.as_slice().as_ptr() as *const _);
         }
 
         let version = unsafe {
@@ -207,22 +327,27 @@ impl Renderer {
 
         println!("OpenGL version {}", version);
 
-        Renderer {
-            cols,
-            rows,
-            cells,
-            needs_rebuild: false,
+        let mut gl_backend: GlBackend = GlBackend {
+            upload_stats: UploadStats { frames: 0, cells_uploaded: 0, bytes_uploaded: 0, buffer_calls: 0, rebuild_bytes: 0 },
+            needs_rebuild: true,
+            shader_program,
+            texture_id: 0,
+            atlas_width: 0,
+            atlas_height: 0,
             vao_id: vao,
             vbo_id: vbo,
             ebo_id: ebo,
             vertex_data: Vec::new(),
-            element_data: Vec::new()
-        }
-    }
+            element_data: Vec::new(),
+            viewport: Viewport { x: 0, y: 0, width: window.width, height: window.height, scale: 1,
+                                 window_width: window.width, window_height: window.height }
+        };
 
-    pub fn clear_cells(&mut self) {
-        self.cells.clear();
-        self.cells.resize((self.cols * self.rows) as usize, SPRITE_NONE);
+        load_font_texture(&mut gl_backend, font);
+        update_projection(&mut gl_backend, cols * font.glyph_width, rows * font.glyph_height, window.width, window.height);
+        create_buffers(&mut gl_backend, LAYERS.len() * (cols * rows) as usize * 2);
+
+        gl_backend
     }
 }
 
@@ -232,9 +357,20 @@ pub fn draw_cell(renderer: &mut Renderer, x: i32, y: i32, sprite: Sprite) {
     }
 
     let index: usize = ((y as u32 * renderer.cols) + x as u32) as usize;
-    if renderer.cells[index] != sprite {
-        renderer.cells[index] = sprite;
-        renderer.needs_rebuild = true;
+    renderer.current_cells()[index] = sprite;
+}
+
+// Changes the background of a rectangle of cells and keeps their glyphs
+pub fn draw_background(renderer: &mut Renderer, x: i32, y: i32, width: u32, height: u32, color: Color) {
+    for row in y..(y + height as i32) {
+        for col in x..(x + width as i32) {
+            if (col < 0) || (row < 0) || (col as u32 >= renderer.cols) || (row as u32 >= renderer.rows) {
+                continue;
+            }
+
+            let index: usize = ((row as u32 * renderer.cols) + col as u32) as usize;
+            renderer.current_cells()[index].background = color;
+        }
     }
 }
 
@@ -242,43 +378,134 @@ pub fn draw_string(renderer: &mut Renderer, x: i32, y: i32, string: &str) {
     let mut x: i32 = x;
 
     for c in string.chars() {
-        let sprite: Sprite = Sprite::new(c, COLOR_WHITE);
+        let sprite: Sprite = Sprite::new(c, renderer.text_color);
         draw_cell(renderer, x, y, sprite);
         x += 1;
     }
 }
 
 pub fn draw_box(renderer: &mut Renderer, x: i32, y: i32, width: u32, height: u32) {
-    draw_cell(renderer, x, y, SPRITE_BOX_BOTTOM_LEFT); // Bottom left
-    draw_cell(renderer, x + (width as i32 - 1), y, SPRITE_BOX_BOTTOM_RIGHT); // Bottom right
-    draw_cell(renderer, x, y + (height as i32 - 1), SPRITE_BOX_TOP_LEFT);  // Top left
-    draw_cell(renderer, x + (width as i32 - 1), y + (height as i32 - 1), SPRITE_BOX_TOP_RIGHT); // Top right
+    let color: Color = renderer.border_color;
+    let bottom_left: Sprite = Sprite::new(SPRITE_BOX_BOTTOM_LEFT.graphic, color);
+    let bottom_right: Sprite = Sprite::new(SPRITE_BOX_BOTTOM_RIGHT.graphic, color);
+    let top_left: Sprite = Sprite::new(SPRITE_BOX_TOP_LEFT.graphic, color);
+    let top_right: Sprite = Sprite::new(SPRITE_BOX_TOP_RIGHT.graphic, color);
+    let horizontal: Sprite = Sprite::new(SPRITE_BOX_HORIZONTAL.graphic, color);
+    let vertical: Sprite = Sprite::new(SPRITE_BOX_VERTICAL.graphic, color);
+
+    draw_cell(renderer, x, y, bottom_left); // Bottom left
+    draw_cell(renderer, x + (width as i32 - 1), y, bottom_right); // Bottom right
+    draw_cell(renderer, x, y + (height as i32 - 1), top_left);  // Top left
+    draw_cell(renderer, x + (width as i32 - 1), y + (height as i32 - 1), top_right); // Top right
 
     for i in (x + 1)..(x + width as i32 - 1) {
-        draw_cell(renderer, i, y, SPRITE_BOX_HORIZONTAL); // Bottom
-        draw_cell(renderer, i, y + (height as i32 - 1), SPRITE_BOX_HORIZONTAL); // Top
+        draw_cell(renderer, i, y, horizontal); // Bottom
+        draw_cell(renderer, i, y + (height as i32 - 1), horizontal); // Top
     }
 
     for i in (y + 1)..(y + height as i32 - 1) {
-        draw_cell(renderer, x, i, SPRITE_BOX_VERTICAL); // Left
-        draw_cell(renderer, x + (width as i32 - 1), i, SPRITE_BOX_VERTICAL); // Right
+        draw_cell(renderer, x, i, vertical); // Left
+        draw_cell(renderer, x + (width as i32 - 1), i, vertical); // Right
     }
 }
 
 pub fn clear(renderer: &mut Renderer) {
     renderer.clear_cells();
-    renderer.needs_rebuild = true;
+    renderer.layer = Layer::World;
 }
 
 pub fn render(renderer: &mut Renderer) {
+    let Renderer { ref mut layers, ref mut backend, ref font, cols, background_color, .. } = *renderer;
+
+    match *backend {
+        Backend::OpenGl(ref mut gl_backend) => { render_gl(gl_backend, layers, cols, font, background_color); },
+        Backend::Software(ref mut software_backend) => { software::rasterize(software_backend, layers, cols, font, background_color); },
+        #[cfg(unix)]
+        Backend::Terminal(ref mut terminal_backend) => { terminal::draw(terminal_backend, layers, cols, background_color); }
+    }
+}
+
+fn render_gl(gl_backend: &mut GlBackend, layers: &mut [CellLayer], cols: u32, font: &Font, background: Color) {
     unsafe {
-        if renderer.needs_rebuild {
-            upload(renderer);
-        }
+        upload(gl_backend, layers, cols, font);
+
+        let viewport: Viewport = gl_backend.viewport;
 
+        gl::ClearColor(LETTERBOX_COLOR.r, LETTERBOX_COLOR.g, LETTERBOX_COLOR.b, 1.0);
         gl::Clear(gl::COLOR_BUFFER_BIT);
-        gl::DrawElements(gl::TRIANGLES, renderer.element_data.len() as i32, gl::UNSIGNED_INT, ptr::null());
+
+        gl::Enable(gl::SCISSOR_TEST);
+        gl::Scissor(viewport.x, viewport.y, viewport.width as GLsizei, viewport.height as GLsizei);
+        gl::ClearColor(background.r, background.g, background.b, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+        gl::Disable(gl::SCISSOR_TEST);
+
+        gl::DrawElements(gl::TRIANGLES, gl_backend.element_data.len() as i32, gl::UNSIGNED_INT, ptr::null());
+    }
+
+    // Only glyphs and backgrounds that show up would have been in the rebuilt mesh
+    let visible_quads: usize = layers.iter()
+        .flat_map(|layer| layer.cells.iter())
+        .map(|cell| (cell.graphic != ' ') as usize + (cell.background.a > 0.0) as usize)
+        .sum();
+
+    gl_backend.upload_stats.frames += 1;
+    gl_backend.upload_stats.rebuild_bytes += (visible_quads * ((QUAD_SIZE * mem::size_of::<f32>()) + (6 * mem::size_of::<u32>()))) as u64;
+}
+
+// Copies the last rendered frame into an image. Terminals have no pixels, so their cells are rasterized.
+pub fn capture_frame(renderer: &Renderer) -> RgbaImage {
+    match renderer.backend {
+        Backend::OpenGl(ref gl_backend) => read_framebuffer(gl_backend.viewport),
+        Backend::Software(ref software_backend) => software_backend.image.clone(),
+        #[cfg(unix)]
+        Backend::Terminal(_) => {
+            let mut software_backend: SoftwareBackend = SoftwareBackend::new(&renderer.font, renderer.cols, renderer.rows);
+            software::rasterize(&mut software_backend, &renderer.layers, renderer.cols, &renderer.font, renderer.background_color);
+            software_backend.image
+        }
+    }
+}
+
+// Reads the grid out of the letterboxed window and scales it back down, so captures are the same size
+// however big the window is. Parts of the grid cropped off by a small window come out black.
+fn read_framebuffer(viewport: Viewport) -> RgbaImage {
+    let width: u32 = viewport.width;
+    let height: u32 = viewport.height;
+    let mut pixels: Vec<u8> = vec![0; (width * height * 4) as usize];
+
+    // Only the part of the grid inside the window can be read
+    let left: i32 = viewport.x.max(0);
+    let bottom: i32 = viewport.y.max(0);
+    let right: i32 = (viewport.x + width as i32).min(viewport.window_width as i32);
+    let top: i32 = (viewport.y + height as i32).min(viewport.window_height as i32);
+
+    if (right > left) && (top > bottom) {
+        let offset: usize = ((((bottom - viewport.y) as u32 * width) + (left - viewport.x) as u32) * 4) as usize;
+
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::PixelStorei(gl::PACK_ROW_LENGTH, width as GLint);
+            gl::ReadPixels(left, bottom, right - left, top - bottom, gl::RGBA, gl::UNSIGNED_BYTE,
+                           pixels[offset..].as_mut_ptr() as *mut _);
+            gl::PixelStorei(gl::PACK_ROW_LENGTH, 0);
+        }
     }
+
+    // Blending leaves the framebuffer's alpha all over the place, but what's on screen is opaque
+    for pixel in pixels.chunks_mut(4) {
+        pixel[3] = 255;
+    }
+
+    // OpenGL reads rows from the bottom up
+    let image: RgbaImage = RgbaImage::from_raw(width, height, pixels).unwrap();
+    let image: RgbaImage = image::imageops::flip_vertical(&image);
+
+    if viewport.scale == 1 {
+        return image;
+    }
+
+    image::imageops::resize(&image, width / viewport.scale, height / viewport.scale, image::FilterType::Nearest)
 }
 
 pub fn display(window: &Window) {
@@ -301,106 +528,332 @@ fn compile_shader(shader_type: GLenum, source: &[u8]) -> GLuint {
     }
 }
 
-fn upload(renderer: &mut Renderer) {
-    renderer.vertex_data.clear();
-    renderer.element_data.clear();
+// Switches to another font atlas. The grid keeps its size, so the window only grows if the bigger cells
+// would no longer fit.
+pub fn set_font(window: &mut Window, renderer: &mut Renderer, font: Font) {
+    renderer.cell_width = font.glyph_width;
+    renderer.cell_height = font.glyph_height;
 
-    // Construct render mesh
-    for row in 0..renderer.rows {
-        for col in 0..renderer.cols {
-            let index: usize = ((row * renderer.cols) + col) as usize;
-            let cell: Sprite = renderer.cells[index];
+    let width: u32 = renderer.cols * renderer.cell_width;
+    let height: u32 = renderer.rows * renderer.cell_height;
 
-            if cell.graphic == ' ' {
-                continue;
+    match renderer.backend {
+        Backend::OpenGl(ref mut gl_backend) => {
+            gl_backend.needs_rebuild = true;
+            load_font_texture(gl_backend, &font);
+
+            if (window.width < width) || (window.height < height) {
+                let window_width: u32 = window.width.max(width);
+                let window_height: u32 = window.height.max(height);
+                set_window_size(window, window_width, window_height);
             }
 
-            let vertex_count: u32 = renderer.vertex_data.len() as u32 / 7;
-            let x_offset: f32 = (col * CELL_WIDTH) as f32;
-            let y_offset: f32 = (row * CELL_HEIGHT) as f32;
-
-            let cols: u8 = 16;
-
-            let ascii: u8 = cell.graphic as u8;
-            let sprite_col: u8 = ascii % cols;
-            let sprite_row: u8 = ascii / cols;
-            let sprite_width: f32 = CELL_WIDTH as f32 / 128.0;
-            let sprite_height: f32 = CELL_HEIGHT as f32 / 256.0;
-            let u: f32 = sprite_col as f32 * sprite_width;
-            let v: f32 = sprite_row as f32 * sprite_height;
-
-            let r: f32 = cell.color.r;
-            let g: f32 = cell.color.g;
-            let b: f32 = cell.color.b;
-            //let a: f32 = cell.color.a;
-
-            let new_vertices: [f32; 28] = [
-                // Top left
-                x_offset, y_offset + CELL_HEIGHT as f32, r, g, b, u, v,
-                // Top right
-                x_offset + CELL_WIDTH as f32, y_offset + CELL_HEIGHT as f32, r, g, b, u + sprite_width, v,
-                // Bottom right
-                x_offset + CELL_WIDTH as f32, y_offset, r, g, b, u + sprite_width, v + sprite_height,
-                // Bottom left
-                x_offset, y_offset, r, g, b, u, v + sprite_height
-            ];
-
-            let new_elements: [u32; 6] = [
-                vertex_count, vertex_count + 1, vertex_count + 2,
-                vertex_count + 2, vertex_count + 3, vertex_count
-            ];
-
-            renderer.vertex_data.extend_from_slice(&new_vertices);
-            renderer.element_data.extend_from_slice(&new_elements);
+            update_projection(gl_backend, width, height, window.width, window.height);
+            window.is_resized = false;
+        },
+        Backend::Software(ref mut software_backend) => {
+            *software_backend = SoftwareBackend::new(&font, renderer.cols, renderer.rows);
+        },
+        #[cfg(unix)]
+        Backend::Terminal(_) => ()
+    }
+
+    renderer.font = font;
+}
+
+fn load_font_texture(gl_backend: &mut GlBackend, font: &Font) {
+    let image: &RgbaImage = &font.atlas;
+
+    gl_backend.atlas_width = image.width();
+    gl_backend.atlas_height = image.height();
+
+    let width: GLint = image.width() as GLint;
+    let height: GLint = image.height() as GLint;
+    let pixels: &[u8] = image;
+
+    unsafe {
+        if gl_backend.texture_id != 0 {
+            gl::DeleteTextures(1, &gl_backend.texture_id);
         }
+
+        let mut texture_id: GLuint = 0;
+        gl::GenTextures(1, &mut texture_id);
+        gl::BindTexture(gl::TEXTURE_2D, texture_id);
+        gl::TexImage2D(gl::TEXTURE_2D,
+                        0,
+                        gl::RGBA8 as GLint,
+                        width,
+                        height,
+                        0,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        pixels.as_ptr() as *const _);
+
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        gl::GenerateMipmap(gl::TEXTURE_2D);
+
+        gl_backend.texture_id = texture_id;
     }
+}
+
+// Fits the renderer to the window after it was resized
+pub fn update_viewport(window: &mut Window, renderer: &mut Renderer) {
+    let width: u32 = renderer.cols * renderer.cell_width;
+    let height: u32 = renderer.rows * renderer.cell_height;
+
+    if let Backend::OpenGl(ref mut gl_backend) = renderer.backend {
+        update_projection(gl_backend, width, height, window.width, window.height);
+    }
+
+    window.is_resized = false;
+}
+
+// The projection always covers the grid in unscaled pixels. Scaling and letterboxing happen in the viewport.
+fn update_projection(gl_backend: &mut GlBackend, width: u32, height: u32, window_width: u32, window_height: u32) {
+    // A window too small for the grid still draws at 1x and crops it
+    let scale: u32 = (window_width / width).min(window_height / height).max(1);
+    let viewport_width: u32 = width * scale;
+    let viewport_height: u32 = height * scale;
+    let free_width: i32 = window_width as i32 - viewport_width as i32;
+    let free_height: i32 = window_height as i32 - viewport_height as i32;
+
+    // Cropping keeps the top left, where the HUD and the start of every line of text are. OpenGL
+    // counts from the bottom, so the grid is pushed down to keep its top in view.
+    gl_backend.viewport = Viewport {
+        x: if free_width >= 0 { free_width / 2 } else { 0 },
+        y: if free_height >= 0 { free_height / 2 } else { free_height },
+        width: viewport_width,
+        height: viewport_height,
+        scale,
+        window_width,
+        window_height
+    };
+
+    unsafe {
+        let translation: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+
+        let model: Matrix4<f32> = Isometry3::new(translation, nalgebra::zero()).to_homogeneous();
+        let view: Matrix4<f32> = Isometry3::new(Vector3::new(0.0, 0.0, -1.0), nalgebra::zero()).to_homogeneous();
+        let projection: Matrix4<f32> = Orthographic3::new(0.0, width as f32, 0.0, height as f32, 0.1, 1000.0).unwrap();
+        let model_view_projection = projection * model * view;
+
+        let uni_model_view_projection = gl::GetUniformLocation(gl_backend.shader_program, b"modelViewProjection\0".as_ptr() as *const _);
+        gl::UniformMatrix4fv(uni_model_view_projection, 1, gl::FALSE, model_view_projection.as_slice().as_ptr() as *const _);
+        let viewport: Viewport = gl_backend.viewport;
+        gl::Viewport(viewport.x, viewport.y, viewport.width as GLsizei, viewport.height as GLsizei);
+    }
+}
+
+// Allocates the vertex buffer once, with a slot for every cell, and fills in the indices which never change
+fn create_buffers(gl_backend: &mut GlBackend, quad_count: usize) {
+    gl_backend.vertex_data.clear();
+    gl_backend.vertex_data.resize(quad_count * QUAD_SIZE, 0.0);
 
-    // Upload vertices
+    gl_backend.element_data.clear();
+    for quad in 0..quad_count as u32 {
+        let vertex_count: u32 = quad * 4;
+        let new_elements: [u32; 6] = [
+            vertex_count, vertex_count + 1, vertex_count + 2,
+            vertex_count + 2, vertex_count + 3, vertex_count
+        ];
+
+        gl_backend.element_data.extend_from_slice(&new_elements);
+    }
 
     unsafe {
-        gl::BindBuffer(gl::ARRAY_BUFFER, renderer.vbo_id);
+        gl::BindBuffer(gl::ARRAY_BUFFER, gl_backend.vbo_id);
         gl::BufferData(gl::ARRAY_BUFFER,
-                       (renderer.vertex_data.len() * mem::size_of::<f32>()) as gl::types::GLsizeiptr,
-                       renderer.vertex_data.as_ptr() as *const _, gl::STATIC_DRAW);
+                       (gl_backend.vertex_data.len() * mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+                       gl_backend.vertex_data.as_ptr() as *const _, gl::DYNAMIC_DRAW);
 
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, renderer.ebo_id);
+        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, gl_backend.ebo_id);
         gl::BufferData(gl::ELEMENT_ARRAY_BUFFER,
-                       (renderer.element_data.len() * mem::size_of::<u32>()) as gl::types::GLsizeiptr,
-                       renderer.element_data.as_ptr() as *const _, gl::STATIC_DRAW);
+                       (gl_backend.element_data.len() * mem::size_of::<u32>()) as gl::types::GLsizeiptr,
+                       gl_backend.element_data.as_ptr() as *const _, gl::STATIC_DRAW);
     }
+}
 
-    renderer.needs_rebuild = false;
+// Rewrites the cells that differ from what the GPU has, sending each run of neighbouring changes in one call
+fn upload(gl_backend: &mut GlBackend, layers: &mut [CellLayer], cols: u32, font: &Font) {
+    let cell_count: usize = layers[0].cells.len();
+    let rebuild_all: bool = gl_backend.needs_rebuild;
+
+    unsafe {
+        gl::BindBuffer(gl::ARRAY_BUFFER, gl_backend.vbo_id);
+    }
+
+    for layer_index in 0..layers.len() {
+        // Layers that haven't changed since the last upload are left alone
+        if !rebuild_all && !layers[layer_index].is_dirty() {
+            continue;
+        }
+
+        let mut run_start: Option<usize> = None;
+
+        // One step past the end so that a run reaching the last cell gets uploaded too
+        for index in 0..(cell_count + 1) {
+            let is_dirty: bool = (index < cell_count) && (rebuild_all || {
+                let layer: &CellLayer = &layers[layer_index];
+                layer.cells[index] != layer.uploaded_cells[index]
+            });
+
+            if is_dirty {
+                let cell: Sprite = layers[layer_index].cells[index];
+                let offset: usize = ((layer_index * cell_count) + index) * CELL_SIZE;
+                build_cell(gl_backend, font, cell, index as u32 % cols, index as u32 / cols, offset);
+                if run_start.is_none() {
+                    run_start = Some(index);
+                }
+            } else if let Some(start) = run_start {
+                upload_cells(gl_backend, (layer_index * cell_count) + start, index - start);
+                run_start = None;
+            }
+        }
+
+        let layer: &mut CellLayer = &mut layers[layer_index];
+        layer.uploaded_cells.clone_from(&layer.cells);
+    }
+
+    gl_backend.needs_rebuild = false;
+}
+
+fn upload_cells(gl_backend: &mut GlBackend, first_slot: usize, count: usize) {
+    let offset: usize = first_slot * CELL_SIZE;
+    let length: usize = count * CELL_SIZE;
+
+    unsafe {
+        gl::BufferSubData(gl::ARRAY_BUFFER,
+                          (offset * mem::size_of::<f32>()) as gl::types::GLintptr,
+                          (length * mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+                          gl_backend.vertex_data[offset..].as_ptr() as *const _);
+    }
+
+    gl_backend.upload_stats.cells_uploaded += count as u64;
+    gl_backend.upload_stats.bytes_uploaded += (length * mem::size_of::<f32>()) as u64;
+    gl_backend.upload_stats.buffer_calls += 1;
+}
+
+fn build_cell(gl_backend: &mut GlBackend, font: &Font, cell: Sprite, col: u32, row: u32, offset: usize) {
+    // Backgrounds are drawn with the solid block glyph so they can share the font texture
+    if cell.background.a > 0.0 {
+        write_quad(gl_backend, font, offset, col, row, GRAPHIC_SOLID, cell.background);
+    } else {
+        clear_quad(gl_backend, offset);
+    }
+
+    if cell.graphic != ' ' {
+        write_quad(gl_backend, font, offset + QUAD_SIZE, col, row, cell.graphic, cell.color);
+    } else {
+        clear_quad(gl_backend, offset + QUAD_SIZE);
+    }
+}
+
+// Collapses a quad to a point so it covers no pixels
+fn clear_quad(gl_backend: &mut GlBackend, offset: usize) {
+    for value in &mut gl_backend.vertex_data[offset..(offset + QUAD_SIZE)] {
+        *value = 0.0;
+    }
+}
+
+fn write_quad(gl_backend: &mut GlBackend, font: &Font, offset: usize, col: u32, row: u32, graphic: char, color: Color) {
+    let cell_width: f32 = font.glyph_width as f32;
+    let cell_height: f32 = font.glyph_height as f32;
+    let x_offset: f32 = col as f32 * cell_width;
+    let y_offset: f32 = row as f32 * cell_height;
+
+    let glyph: u32 = cp437::to_cp437_or_fallback(graphic) as u32 % (font.columns * font.rows);
+    let sprite_col: u32 = glyph % font.columns;
+    let sprite_row: u32 = glyph / font.columns;
+    let sprite_width: f32 = font.glyph_width as f32 / gl_backend.atlas_width as f32;
+    let sprite_height: f32 = font.glyph_height as f32 / gl_backend.atlas_height as f32;
+    let u: f32 = (sprite_col * (font.glyph_width + font.spacing)) as f32 / gl_backend.atlas_width as f32;
+    let v: f32 = (sprite_row * (font.glyph_height + font.spacing)) as f32 / gl_backend.atlas_height as f32;
+
+    let r: f32 = color.r;
+    let g: f32 = color.g;
+    let b: f32 = color.b;
+    let a: f32 = color.a;
+
+    let new_vertices: [f32; QUAD_SIZE] = [
+        // Top left
+        x_offset, y_offset + cell_height, r, g, b, a, u, v,
+        // Top right
+        x_offset + cell_width, y_offset + cell_height, r, g, b, a, u + sprite_width, v,
+        // Bottom right
+        x_offset + cell_width, y_offset, r, g, b, a, u + sprite_width, v + sprite_height,
+        // Bottom left
+        x_offset, y_offset, r, g, b, a, u, v + sprite_height
+    ];
+
+    gl_backend.vertex_data[offset..(offset + QUAD_SIZE)].copy_from_slice(&new_vertices);
 }
 
-pub const SPRITE_NONE: Sprite = Sprite { graphic: ' ', color: COLOR_WHITE };
-pub const SPRITE_BOX_BOTTOM_LEFT: Sprite = Sprite { graphic: 192 as char, color: COLOR_WHITE };
-pub const SPRITE_BOX_BOTTOM_RIGHT: Sprite = Sprite { graphic: 217 as char, color: COLOR_WHITE };
-pub const SPRITE_BOX_TOP_LEFT: Sprite = Sprite { graphic: 218 as char, color: COLOR_WHITE };
-pub const SPRITE_BOX_TOP_RIGHT: Sprite = Sprite { graphic: 191 as char, color: COLOR_WHITE };
-pub const SPRITE_BOX_HORIZONTAL: Sprite = Sprite { graphic: 196 as char, color: COLOR_WHITE };
-pub const SPRITE_BOX_VERTICAL: Sprite = Sprite { graphic: 179 as char, color: COLOR_WHITE };
+// Prints how much was uploaded compared to rebuilding and re-uploading the whole buffer every frame
+pub fn print_upload_stats(renderer: &Renderer) {
+    let gl_backend: &GlBackend = match renderer.backend {
+        Backend::OpenGl(ref gl_backend) => gl_backend,
+        _ => { return; }
+    };
+
+    let stats: UploadStats = gl_backend.upload_stats;
+    let cells_per_frame: f64 = stats.cells_uploaded as f64 / stats.frames.max(1) as f64;
+    let saved_percent: f64 = 100.0 - (stats.bytes_uploaded as f64 * 100.0 / stats.rebuild_bytes.max(1) as f64);
+
+    println!("Uploaded {:.1} cells per frame over {} frames in {} calls", cells_per_frame, stats.frames, stats.buffer_calls);
+    println!("Uploaded {} KiB instead of {} KiB for a rebuilt mesh ({:.1}% saved)",
+             stats.bytes_uploaded / 1024, stats.rebuild_bytes / 1024, saved_percent);
+}
+
+pub const GRAPHIC_SOLID: char = '█';
+
+pub const SPRITE_NONE: Sprite = Sprite { graphic: ' ', color: COLOR_WHITE, background: COLOR_TRANSPARENT };
+pub const SPRITE_BOX_BOTTOM_LEFT: Sprite = Sprite { graphic: '└', color: COLOR_WHITE, background: COLOR_TRANSPARENT };
+pub const SPRITE_BOX_BOTTOM_RIGHT: Sprite = Sprite { graphic: '┘', color: COLOR_WHITE, background: COLOR_TRANSPARENT };
+pub const SPRITE_BOX_TOP_LEFT: Sprite = Sprite { graphic: '┌', color: COLOR_WHITE, background: COLOR_TRANSPARENT };
+pub const SPRITE_BOX_TOP_RIGHT: Sprite = Sprite { graphic: '┐', color: COLOR_WHITE, background: COLOR_TRANSPARENT };
+pub const SPRITE_BOX_HORIZONTAL: Sprite = Sprite { graphic: '─', color: COLOR_WHITE, background: COLOR_TRANSPARENT };
+pub const SPRITE_BOX_VERTICAL: Sprite = Sprite { graphic: '│', color: COLOR_WHITE, background: COLOR_TRANSPARENT };
 
 #[derive(Copy, Clone, PartialEq)]
 pub struct Sprite {
     pub graphic: char,
-    pub color: Color
+    pub color: Color,
+    pub background: Color // Filled in beneath the glyph unless fully transparent
 }
 
 impl Sprite {
     pub fn new(graphic: char, color: Color) -> Sprite {
         Sprite {
             graphic,
-            color
+            color,
+            background: COLOR_TRANSPARENT
+        }
+    }
+
+    pub fn with_background(graphic: char, color: Color, background: Color) -> Sprite {
+        Sprite {
+            graphic,
+            color,
+            background
         }
     }
 }
 
+pub const COLOR_TRANSPARENT: Color = Color { r: 0.0, g: 0.0, b: 0.0, a: 0.0 };
 pub const COLOR_BLACK: Color = Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
 pub const COLOR_WHITE: Color = Color { r: 1.0, g: 1.0, b: 1.0, a: 1.0 };
 pub const COLOR_GRAY: Color = Color { r: 0.4, g: 0.4, b: 0.4, a: 1.0 };
 pub const COLOR_RED: Color = Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 };
 pub const COLOR_GREEN: Color = Color { r: 0.0, g: 1.0, b: 0.0, a: 1.0 };
 pub const COLOR_BLUE: Color = Color { r: 0.0, g: 0.0, b: 1.0, a: 1.0 };
+pub const COLOR_YELLOW: Color = Color { r: 1.0, g: 1.0, b: 0.0, a: 1.0 };
+pub const COLOR_CYAN: Color = Color { r: 0.0, g: 1.0, b: 1.0, a: 1.0 };
+pub const COLOR_MAGENTA: Color = Color { r: 1.0, g: 0.0, b: 1.0, a: 1.0 };
+pub const COLOR_ORANGE: Color = Color { r: 1.0, g: 0.5, b: 0.0, a: 1.0 };
+pub const COLOR_PURPLE: Color = Color { r: 0.6, g: 0.2, b: 0.8, a: 1.0 };
 
 #[derive(Copy, Clone, PartialEq)]
 pub struct Color {
@@ -419,4 +872,52 @@ impl Color {
             a: a as f32 / 255.0
         }
     }
-}
\ No newline at end of file
+}
+
+pub fn color_to_bytes(color: Color) -> [u8; 4] {
+    [
+        (color.r * 255.0).round() as u8,
+        (color.g * 255.0).round() as u8,
+        (color.b * 255.0).round() as u8,
+        (color.a * 255.0).round() as u8
+    ]
+}
+
+pub fn color_with_alpha(color: Color, alpha: f32) -> Color {
+    Color {
+        r: color.r,
+        g: color.g,
+        b: color.b,
+        a: alpha.max(0.0).min(1.0)
+    }
+}
+
+// Hue in degrees, saturation and value from 0 to 1
+pub fn color_from_hsv(hue: f32, saturation: f32, value: f32) -> Color {
+    let hue: f32 = ((hue % 360.0) + 360.0) % 360.0;
+    let chroma: f32 = value * saturation;
+    let x: f32 = chroma * (1.0 - (((hue / 60.0) % 2.0) - 1.0).abs());
+    let m: f32 = value - chroma;
+
+    let (r, g, b): (f32, f32, f32) = match (hue / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x)
+    };
+
+    Color { r: r + m, g: g + m, b: b + m, a: 1.0 }
+}
+
+pub fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let t: f32 = t.max(0.0).min(1.0);
+
+    Color {
+        r: from.r + ((to.r - from.r) * t),
+        g: from.g + ((to.g - from.g) * t),
+        b: from.b + ((to.b - from.b) * t),
+        a: from.a + ((to.a - from.a) * t)
+    }
+}
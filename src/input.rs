@@ -44,7 +44,7 @@ pub fn process_events(window: &mut Window, input_man: &mut InputMan) {
         match event {
             Event::WindowEvent { event, .. } => match event {
                 WindowEvent::Closed => { window.is_close_requested = true; },
-                WindowEvent::Resized(w, h) => { println!("Resize to {}, {}", w, h); gfx::resize_window(window, w, h); },
+                WindowEvent::Resized(w, h) => { gfx::resize_window(window, w, h); },
                 WindowEvent::KeyboardInput { input, .. } => { input::process_key_input(input_man, &input); },
                 _ => ()
             },
@@ -58,6 +58,18 @@ pub fn update_input(input_man: &mut InputMan) {
     input_man.released_keys.clear();
 }
 
+// For input sources without release events, like terminals. Every press counts, even while the key is held.
+#[allow(dead_code)]
+pub fn press_key(input_man: &mut InputMan, keycode: VirtualKeyCode) {
+    input_man.pressed_keys.insert(keycode, true);
+    input_man.current_keys.insert(keycode, true);
+}
+
+pub fn release_key(input_man: &mut InputMan, keycode: VirtualKeyCode) {
+    input_man.released_keys.insert(keycode, true);
+    input_man.current_keys.insert(keycode, false);
+}
+
 fn process_key_input(input_man: &mut InputMan, event: &KeyboardInput) {
     let keycode: VirtualKeyCode = event.virtual_keycode.unwrap();
 
@@ -70,10 +82,7 @@ fn process_key_input(input_man: &mut InputMan, event: &KeyboardInput) {
 
             input_man.current_keys.insert(keycode, true);
         },
-        ElementState::Released => {
-            input_man.released_keys.insert(keycode, true);
-            input_man.current_keys.insert(keycode, false);
-        }
+        ElementState::Released => { release_key(input_man, keycode); }
     }
 }
 
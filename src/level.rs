@@ -0,0 +1,259 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use ::*;
+
+pub const PROGRESS_FILE_PATH: &'static str = "progress.txt";
+
+// Layouts cover the 27x12 playfield inside the border, top row first.
+// '#' is an obstacle and each digit marks one end of a portal pair.
+pub const LAYOUT_HEIGHT: i32 = 12;
+
+pub enum Goal {
+    None,
+    ReachLength(usize),
+    CollectFood { count: u32, seconds: i64 },
+    Survive(i64)
+}
+
+#[derive(Clone)]
+pub struct Portal {
+    pub a: Coordinates,
+    pub b: Coordinates,
+    pub id: u32
+}
+
+pub struct Level {
+    pub name: &'static str,
+    pub layout: &'static [&'static str],
+    pub start_x: i32,
+    pub start_y: i32,
+    pub move_frequency_ms: i64,
+    pub growth_per_food: u32,
+    pub goal: Goal
+}
+
+const LAYOUT_EMPTY: &'static [&'static str] = &[
+    "                           ",
+    "                           ",
+    "                           ",
+    "                           ",
+    "                           ",
+    "                           ",
+    "                           ",
+    "                           ",
+    "                           ",
+    "                           ",
+    "                           ",
+    "                           "
+];
+
+pub const LEVEL_ENDLESS: Level = Level {
+    name: "Endless",
+    layout: LAYOUT_EMPTY,
+    start_x: 15,
+    start_y: 8,
+    move_frequency_ms: BASE_MOVE_FREQUENCY_MS,
+    growth_per_food: 1,
+    goal: Goal::None
+};
+
+pub const LEVELS: &'static [Level] = &[
+    Level {
+        name: "Open Field",
+        layout: LAYOUT_EMPTY,
+        start_x: 15,
+        start_y: 8,
+        move_frequency_ms: 110,
+        growth_per_food: 1,
+        goal: Goal::ReachLength(8)
+    },
+    Level {
+        name: "Pillars",
+        layout: &[
+            "                           ",
+            "                           ",
+            "    ##               ##    ",
+            "    ##               ##    ",
+            "                           ",
+            "                           ",
+            "                           ",
+            "                           ",
+            "    ##               ##    ",
+            "    ##               ##    ",
+            "                           ",
+            "                           "
+        ],
+        start_x: 15,
+        start_y: 8,
+        move_frequency_ms: 100,
+        growth_per_food: 1,
+        goal: Goal::CollectFood { count: 10, seconds: 60 }
+    },
+    Level {
+        name: "Divide",
+        layout: &[
+            "                           ",
+            "                           ",
+            "                           ",
+            "###########     ###########",
+            "                           ",
+            "                           ",
+            "                           ",
+            "                           ",
+            "###########     ###########",
+            "                           ",
+            "                           ",
+            "                           "
+        ],
+        start_x: 15,
+        start_y: 6,
+        move_frequency_ms: 90,
+        growth_per_food: 1,
+        goal: Goal::Survive(45)
+    },
+    Level {
+        name: "Courtyard",
+        layout: &[
+            "                           ",
+            "                           ",
+            "   #########   #########   ",
+            "   #                   #   ",
+            "   #                   #   ",
+            "                           ",
+            "                           ",
+            "   #                   #   ",
+            "   #                   #   ",
+            "   #########   #########   ",
+            "                           ",
+            "                           "
+        ],
+        start_x: 15,
+        start_y: 8,
+        move_frequency_ms: 85,
+        growth_per_food: 2,
+        goal: Goal::ReachLength(20)
+    },
+    Level {
+        name: "Maze",
+        layout: &[
+            "                           ",
+            " ######## ####### ######## ",
+            "                           ",
+            "      #             #      ",
+            "      #             #      ",
+            "      #    #####    #      ",
+            "      #             #      ",
+            "      #             #      ",
+            "                           ",
+            " ######## ####### ######## ",
+            "                           ",
+            "                           "
+        ],
+        start_x: 15,
+        start_y: 8,
+        move_frequency_ms: 80,
+        growth_per_food: 1,
+        goal: Goal::CollectFood { count: 15, seconds: 90 }
+    },
+    Level {
+        name: "Wormholes",
+        layout: &[
+            "                           ",
+            "  1                     2  ",
+            "                           ",
+            "         #########         ",
+            "         #       #         ",
+            "         #       #         ",
+            "         #       #         ",
+            "         #       #         ",
+            "         #########         ",
+            "                           ",
+            "  2                     1  ",
+            "                           "
+        ],
+        start_x: 4,
+        start_y: 6,
+        move_frequency_ms: 90,
+        growth_per_food: 2,
+        goal: Goal::ReachLength(15)
+    }
+];
+
+pub fn build_obstacles(level: &Level) -> Vec<Coordinates> {
+    let mut obstacles: Vec<Coordinates> = Vec::new();
+
+    for (row, line) in level.layout.iter().enumerate() {
+        for (col, c) in line.chars().enumerate() {
+            if c == '#' {
+                obstacles.push(layout_to_coordinates(col as i32, row as i32));
+            }
+        }
+    }
+
+    obstacles
+}
+
+pub fn build_portals(level: &Level) -> Vec<Portal> {
+    let mut portals: Vec<Portal> = Vec::new();
+
+    for id in 1..10 {
+        let mut ends: Vec<Coordinates> = Vec::new();
+        for (row, line) in level.layout.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                if c.to_digit(10) == Some(id) {
+                    ends.push(layout_to_coordinates(col as i32, row as i32));
+                }
+            }
+        }
+
+        if ends.len() == 2 {
+            portals.push(Portal { a: ends[0].clone(), b: ends[1].clone(), id });
+        } else if !ends.is_empty() {
+            println!("Portal {} in level \"{}\" needs exactly two ends!", id, level.name);
+        }
+    }
+
+    portals
+}
+
+// Returns the partner tile of the portal at the given position, if there is one
+pub fn portal_exit(portals: &[Portal], position: &Coordinates) -> Option<Coordinates> {
+    for portal in portals {
+        if portal.a == *position {
+            return Some(portal.b.clone());
+        } else if portal.b == *position {
+            return Some(portal.a.clone());
+        }
+    }
+
+    None
+}
+
+fn layout_to_coordinates(col: i32, row: i32) -> Coordinates {
+    Coordinates::new(col + 1, LAYOUT_HEIGHT - row)
+}
+
+// Returns the number of unlocked campaign levels (always at least one).
+pub fn load_progress() -> usize {
+    let mut contents: String = String::new();
+    match File::open(PROGRESS_FILE_PATH) {
+        Ok(mut file) => {
+            if file.read_to_string(&mut contents).is_err() {
+                return 1;
+            }
+        },
+        Err(_) => { return 1; }
+    }
+
+    let unlocked: usize = contents.trim().parse().unwrap_or(1);
+    unlocked.max(1).min(LEVELS.len())
+}
+
+pub fn save_progress(unlocked: usize) {
+    let result = File::create(PROGRESS_FILE_PATH)
+        .and_then(|mut file| write!(file, "{}", unlocked));
+
+    if let Err(e) = result {
+        println!("Failed to save progress: {}", e);
+    }
+}
@@ -6,22 +6,76 @@ extern crate nalgebra;
 extern crate alga;
 extern crate rand;
 extern crate time;
+extern crate gif;
 
 mod gfx;
 mod input;
+mod level;
+mod powerup;
+mod food;
+mod rng;
+mod save;
+mod rewind;
+mod death;
+mod particle;
+mod boost;
+mod theme;
+mod skin;
+mod config;
+mod font;
+mod cp437;
+mod text;
+mod software;
+#[cfg(unix)]
+mod terminal;
+mod screenshot;
+mod recording;
 
 use rand::*;
 use gfx::*;
 use input::*;
+use level::*;
+use powerup::*;
+use food::*;
+use rng::*;
+use rewind::*;
+use death::*;
+use particle::*;
+use boost::*;
+use theme::*;
+use skin::*;
+use font::*;
+use text::*;
+#[cfg(unix)]
+use terminal::*;
+use screenshot::*;
+use recording::*;
 use glutin::VirtualKeyCode;
 use time::*;
+use std::collections::VecDeque;
 
+pub const GRID_COLS: u32 = 30;
+pub const GRID_ROWS: u32 = 16;
+pub const FONT_KEY: VirtualKeyCode = VirtualKeyCode::F;
+pub const HELP_KEY: VirtualKeyCode = VirtualKeyCode::H;
+pub const HELP_TEXT: &'static str = "Move with W A S D.\nHold SHIFT to boost.\nHold BACKSPACE after dying to rewind.\nESC saves and opens the menu.\nT changes theme, F font.\nF9 records a GIF, F12 a screenshot.\n\nPress H to close.";
 pub const MAX_MOVE_FREQUENCY_MS: i64 = 30;
 pub const BASE_MOVE_FREQUENCY_MS: i64 = 100;
-pub const SPRITE_SNAKE: Sprite = Sprite { graphic: 1 as char, color: COLOR_WHITE };
-pub const SPRITE_FOOD: Sprite = Sprite { graphic: '$', color: COLOR_GREEN };
+pub const SPRITE_SNAKE: Sprite = Sprite { graphic: '☺', color: COLOR_WHITE, background: COLOR_TRANSPARENT };
+pub const SPRITE_FOOD: Sprite = Sprite { graphic: '$', color: COLOR_GREEN, background: COLOR_TRANSPARENT };
+pub const SPRITE_FOOD_BONUS: Sprite = Sprite { graphic: '♦', color: COLOR_YELLOW, background: COLOR_TRANSPARENT };
+pub const SPRITE_FOOD_POISON: Sprite = Sprite { graphic: '♠', color: COLOR_PURPLE, background: COLOR_TRANSPARENT };
+pub const SPRITE_FOOD_SPEED: Sprite = Sprite { graphic: '»', color: COLOR_ORANGE, background: COLOR_TRANSPARENT };
+pub const SPRITE_OBSTACLE: Sprite = Sprite { graphic: '▓', color: COLOR_GRAY, background: COLOR_TRANSPARENT };
+pub const SPRITE_DEATH: Sprite = Sprite { graphic: 'X', color: COLOR_RED, background: COLOR_TRANSPARENT };
+pub const SPRITE_PORTAL: Sprite = Sprite { graphic: '○', color: COLOR_CYAN, background: COLOR_TRANSPARENT };
+pub const GHOST_ALPHA: f32 = 0.4;
 
-#[derive(PartialEq)]
+// End of game panels let the dying snake show through
+pub const PANEL_ALPHA: f32 = 0.75;
+pub const PORTAL_COLORS: [Color; 4] = [COLOR_CYAN, COLOR_MAGENTA, COLOR_YELLOW, COLOR_BLUE];
+
+#[derive(Copy, Clone, PartialEq)]
 pub enum Direction {
     None,
     North,
@@ -32,16 +86,36 @@ pub enum Direction {
 
 #[derive(PartialEq)]
 pub enum GameState {
+    Menu,
     Playing,
-    GameOver
+    GameOver,
+    LevelComplete
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum GameMode {
+    Endless,
+    Campaign
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum MenuItem {
+    Continue,
+    Endless,
+    Campaign,
+    Skin
 }
 
+#[derive(Clone)]
 pub struct Snake {
     segments: Vec<Coordinates>,
     direction: Direction,
     move_frequency: Duration,
     last_move_time: Tm,
-    has_moved: bool
+    has_moved: bool,
+    pending_growth: u32, // Segments still to be added at the tail, one per move
+    moves: u32,
+    births: Vec<u32> // Move count at which each segment was added
 }
 
 impl Snake {
@@ -54,32 +128,90 @@ impl Snake {
             direction: Direction::None,
             move_frequency: time::Duration::milliseconds(100),
             last_move_time: time::now(),
-            has_moved: false
+            has_moved: false,
+            pending_growth: 0,
+            moves: 0,
+            births: vec![0]
         }
     }
 }
 
-fn grow_snake(snake: &mut Snake) {
-    let head = snake.segments.first().unwrap().clone();
-    snake.segments.push(head);
+fn grow_snake(snake: &mut Snake, amount: u32) {
+    snake.pending_growth += amount;
+}
+
+fn shrink_snake(snake: &mut Snake, amount: usize) {
+    let new_length: usize = snake.segments.len().saturating_sub(amount).max(1);
+    snake.segments.truncate(new_length);
+    snake.births.truncate(new_length);
 }
 
 pub struct Game {
     snake: Snake,
-    food_position: Option<Coordinates>,
+    foods: Vec<Food>,
+    obstacles: Vec<Coordinates>,
+    portals: Vec<Portal>,
     score: u32,
-    state: GameState
+    state: GameState,
+    mode: GameMode,
+    level_index: usize,
+    unlocked_levels: usize,
+    menu_selection: usize,
+    is_help_open: bool,
+    food_collected: u32,
+    level_time: Duration,
+    power_up: Option<PowerUp>,
+    effects: Vec<ActiveEffect>,
+    rng: GameRng,
+    has_save: bool,
+    history: VecDeque<Snapshot>,
+    rewind_charges: u32,
+    is_rewinding: bool,
+    rewind_timer: Duration,
+    death: Option<Death>,
+    particles: Vec<Particle>,
+    is_boosting: bool,
+    boost_meter: Duration,
+    boost_cost_timer: Duration,
+    themes: Vec<Theme>,
+    theme_index: usize,
+    skin_index: usize
 }
 
 impl Game {
     pub fn new() -> Game {
-        let initial_snake_position: Coordinates = Coordinates::new(15, 8);
+        let initial_snake_position: Coordinates = Coordinates::new(LEVEL_ENDLESS.start_x, LEVEL_ENDLESS.start_y);
 
         Game {
             snake: Snake::new(initial_snake_position),
-            food_position: None,
+            foods: Vec::new(),
+            obstacles: Vec::new(),
+            portals: Vec::new(),
             score: 0,
-            state: GameState::Playing
+            state: GameState::Menu,
+            mode: GameMode::Endless,
+            level_index: 0,
+            unlocked_levels: level::load_progress(),
+            menu_selection: 0,
+            is_help_open: false,
+            food_collected: 0,
+            level_time: time::Duration::zero(),
+            power_up: None,
+            effects: Vec::new(),
+            rng: GameRng::from_time(),
+            has_save: save::has_save(),
+            history: VecDeque::new(),
+            rewind_charges: REWIND_CHARGES,
+            is_rewinding: false,
+            rewind_timer: time::Duration::zero(),
+            death: None,
+            particles: Vec::new(),
+            is_boosting: false,
+            boost_meter: time::Duration::milliseconds(BOOST_METER_MS),
+            boost_cost_timer: time::Duration::zero(),
+            themes: theme::load_themes(),
+            theme_index: 0,
+            skin_index: 0
         }
     }
 }
@@ -100,16 +232,45 @@ impl Coordinates {
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if (args.len() == 3) && (args[1] == "--render") {
+        render_headless(&args[2]);
+        return;
+    }
+
+    // Raw terminal input needs termios, so this is only available on Unix
+    #[cfg(unix)]
+    {
+        if (args.len() == 2) && (args[1] == "--terminal") {
+            run_in_terminal();
+            return;
+        }
+    }
+
+    // Saves the first frame, i.e. the title screen, and quits. Without a path it goes in the
+    // screenshots folder like the ones taken with F12.
+    let screenshot_arg: Option<String> = if (args.len() == 3) && (args[1] == "--screenshot") {
+        Some(args[2].clone())
+    } else if (args.len() == 2) && (args[1] == "--screenshot") {
+        Some(screenshot::screenshot_path())
+    } else {
+        None
+    };
+
+    // Prints how much vertex data went to the GPU when the game closes
+    let is_upload_stats_enabled: bool = (args.len() == 2) && (args[1] == "--upload-stats");
+
     let window_title: &str = "Rostige Schlange";
-    let window_width: u32 = 30 * gfx::CELL_WIDTH;
-    let window_height: u32 = 15 * gfx::CELL_HEIGHT;
+    let fonts: Vec<Font> = font::load_fonts();
+    let mut font_index: usize = 0;
+    let window_width: u32 = GRID_COLS * fonts[font_index].glyph_width;
+    let window_height: u32 = GRID_ROWS * fonts[font_index].glyph_height;
 
     let mut window: Window = Window::new(window_title, window_width, window_height);
-    let mut renderer: Renderer = Renderer::new(&window);
+    let mut renderer: Renderer = Renderer::new(&window, fonts[font_index].clone());
     let mut input_man: InputMan = InputMan::new();
 
     let mut game: Game = Game::new();
-    reset_food(&mut game);
 
     let frame_time: Duration = time::Duration::milliseconds(16);
     let one_second: Duration = time::Duration::seconds(1);
@@ -120,26 +281,63 @@ fn main() {
     let mut fps_counter: u32 = 0;
     let mut fps: u32 = 0;
 
+    let mut recording: Option<Recording> = None;
+
     loop {
         input::process_events(&mut window, &mut input_man);
         if window.is_close_requested {
+            if game.state == GameState::Playing {
+                save::save_game(&game);
+            }
+
+            if let Some(finished) = recording.take() {
+                recording::finish_recording(finished);
+            }
+
+            if is_upload_stats_enabled {
+                gfx::print_upload_stats(&renderer);
+            }
+
             break;
         }
 
+        if window.is_resized {
+            gfx::update_viewport(&mut window, &mut renderer);
+        }
+
         let delta_time: Duration = time::now() - last_frame_time;
         last_frame_time = time::now();
 
         frame_timer = frame_timer + delta_time;
         if frame_timer >= frame_time {
+            let elapsed_time: Duration = frame_timer;
             frame_timer = time::Duration::zero();
 
-            update(&mut input_man, &mut game);
+            // Switching fonts resizes the window, so it is handled here rather than in update
+            if input::is_key_pressed(&input_man, FONT_KEY) {
+                font_index = (font_index + 1) % fonts.len();
+                gfx::set_font(&mut window, &mut renderer, fonts[font_index].clone());
+            }
+
+            update(&mut input_man, &mut game, elapsed_time);
 
             gfx::clear(&mut renderer);
 
             render(&mut renderer, &mut game);
 
             gfx::render(&mut renderer);
+
+            if let Some(ref path) = screenshot_arg {
+                screenshot::save_screenshot(&renderer, path);
+                break;
+            }
+
+            if input::is_key_pressed(&input_man, SCREENSHOT_KEY) {
+                screenshot::save_screenshot(&renderer, &screenshot::screenshot_path());
+            }
+
+            recording::update_recording(&mut recording, &input_man, current_theme(&game), &renderer, elapsed_time);
+
             gfx::display(&window);
 
             last_frame_time = time::now();
@@ -157,106 +355,840 @@ fn main() {
     }
 }
 
+// Renders the title screen on the CPU and saves it, for machines without a GPU
+fn render_headless(path: &str) {
+    let fonts: Vec<Font> = font::load_fonts();
+    let mut renderer: Renderer = Renderer::new_software(GRID_COLS, GRID_ROWS, fonts[0].clone());
+    let mut game: Game = Game::new();
+
+    render(&mut renderer, &mut game);
+    gfx::render(&mut renderer);
+    screenshot::save_screenshot(&renderer, path);
+}
+
+// Plays in the terminal the program was started from, without a window. Ctrl+C quits.
+#[cfg(unix)]
+fn run_in_terminal() {
+    let mut terminal: Terminal = match Terminal::new() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            println!("Failed to start in the terminal: {}", e);
+            return;
+        }
+    };
+
+    let fonts: Vec<Font> = font::load_fonts();
+    let mut renderer: Renderer = Renderer::new_terminal(GRID_COLS, GRID_ROWS, fonts[0].clone());
+    let mut input_man: InputMan = InputMan::new();
+    let mut game: Game = Game::new();
+
+    let frame_time: Duration = time::Duration::milliseconds(16);
+    let mut last_frame_time: Tm = time::now();
+
+    let mut recording: Option<Recording> = None;
+
+    loop {
+        terminal::process_input(&mut terminal, &mut input_man);
+        if terminal.is_close_requested {
+            if game.state == GameState::Playing {
+                save::save_game(&game);
+            }
+
+            if let Some(finished) = recording.take() {
+                recording::finish_recording(finished);
+            }
+
+            break;
+        }
+
+        let elapsed_time: Duration = time::now() - last_frame_time;
+        last_frame_time = time::now();
+
+        update(&mut input_man, &mut game, elapsed_time);
+
+        gfx::clear(&mut renderer);
+
+        render(&mut renderer, &mut game);
+
+        gfx::render(&mut renderer);
+
+        // Stdout is gone at this point, so the error goes to stderr and the game saves on the next loop
+        if let Backend::Terminal(ref terminal_backend) = renderer.backend {
+            if let Some(ref e) = terminal_backend.output_error {
+                eprintln!("Lost the terminal: {}", e);
+                terminal.is_close_requested = true;
+            }
+        }
+
+        if input::is_key_pressed(&input_man, SCREENSHOT_KEY) {
+            screenshot::save_screenshot(&renderer, &screenshot::screenshot_path());
+        }
+
+        let was_recording: bool = recording.is_some();
+        recording::update_recording(&mut recording, &input_man, current_theme(&game), &renderer, elapsed_time);
+
+        // Screenshots and recordings report to stderr, which lands on top of the grid in raw mode
+        let is_message_printed: bool = input::is_key_pressed(&input_man, SCREENSHOT_KEY) ||
+            input::is_key_pressed(&input_man, RECORD_KEY) || (recording.is_some() != was_recording);
+        if is_message_printed {
+            if let Backend::Terminal(ref mut terminal_backend) = renderer.backend {
+                terminal::force_redraw(terminal_backend);
+            }
+        }
+
+        input::update_input(&mut input_man);
+
+        std::thread::sleep(frame_time.to_std().unwrap());
+    }
+}
+
+fn current_level(game: &Game) -> &'static Level {
+    match game.mode {
+        GameMode::Endless => &LEVEL_ENDLESS,
+        GameMode::Campaign => &LEVELS[game.level_index]
+    }
+}
+
+fn is_cell_free(game: &Game, position: &Coordinates) -> bool {
+    if game.obstacles.contains(position) || game.snake.segments.contains(position) {
+        return false;
+    }
+
+    if level::portal_exit(&game.portals, position).is_some() {
+        return false;
+    }
+
+    if game.foods.iter().any(|food| food.position == *position) {
+        return false;
+    }
+
+    if let Some(ref power_up) = game.power_up {
+        if power_up.position == *position {
+            return false;
+        }
+    }
+
+    true
+}
+
+// None once a long snake has filled the board
+fn random_free_position(game: &mut Game) -> Option<Coordinates> {
+    let mut free_cells: Vec<Coordinates> = Vec::new();
+    for y in 2..12 {
+        for x in 2..28 {
+            let position: Coordinates = Coordinates::new(x, y);
+            if is_cell_free(game, &position) {
+                free_cells.push(position);
+            }
+        }
+    }
+
+    if free_cells.is_empty() {
+        return None;
+    }
+
+    let index: usize = game.rng.gen_range(0, free_cells.len());
+    Some(free_cells.swap_remove(index))
+}
+
 fn reset_food(game: &mut Game) {
-    let mut rng = rand::thread_rng();
-    let x: i32 = rng.gen_range(2, 28);
-    let y: i32 = rng.gen_range(2, 12);
+    game.foods.clear();
+    ensure_food(game);
+}
+
+fn spawn_food(game: &mut Game, kind: FoodKind) {
+    if game.foods.len() >= MAX_FOOD_COUNT {
+        return;
+    }
+
+    if let Some(position) = random_free_position(game) {
+        game.foods.push(Food::new(kind, position));
+    }
+}
+
+// There is always at least one normal food on the board
+fn ensure_food(game: &mut Game) {
+    if !game.foods.iter().any(|food| food.kind == FoodKind::Normal) {
+        if let Some(position) = random_free_position(game) {
+            game.foods.push(Food::new(FoodKind::Normal, position));
+        }
+    }
+}
+
+fn update_foods(game: &mut Game, delta_time: Duration) {
+    for food in &mut game.foods {
+        if let Some(lifetime) = food.lifetime {
+            food.lifetime = Some(lifetime - delta_time);
+        }
+    }
+
+    game.foods.retain(|food| match food.lifetime {
+        Some(lifetime) => lifetime > time::Duration::zero(),
+        None => true
+    });
+}
+
+fn spawn_power_up(game: &mut Game) {
+    if game.power_up.is_some() || (game.rng.gen_range(0, POWERUP_SPAWN_CHANCE) != 0) {
+        return;
+    }
+
+    let kind: PowerUpKind = POWERUP_KINDS[game.rng.gen_range(0, POWERUP_KINDS.len())];
+    if let Some(position) = random_free_position(game) {
+        game.power_up = Some(PowerUp::new(kind, position));
+    }
+}
+
+fn collect_power_up(game: &mut Game, kind: PowerUpKind) {
+    game.power_up = None;
+
+    if kind == PowerUpKind::Shrink {
+        shrink_snake(&mut game.snake, SHRINK_SEGMENTS);
+        return;
+    }
+
+    add_effect(game, kind);
+}
+
+// Adding an effect that is already active restarts its timer
+fn add_effect(game: &mut Game, kind: PowerUpKind) {
+    game.effects.retain(|effect| effect.kind != kind);
+    game.effects.push(ActiveEffect::new(kind));
+}
+
+fn is_effect_active(game: &Game, kind: PowerUpKind) -> bool {
+    game.effects.iter().any(|effect| effect.kind == kind)
+}
+
+fn update_effects(game: &mut Game, delta_time: Duration) {
+    for effect in &mut game.effects {
+        effect.remaining = effect.remaining - delta_time;
+    }
+
+    game.effects.retain(|effect| effect.remaining > time::Duration::zero());
 
-    game.food_position = Some(Coordinates::new(x, y));
+    let mut expired: bool = false;
+    if let Some(ref mut power_up) = game.power_up {
+        power_up.lifetime = power_up.lifetime - delta_time;
+        expired = power_up.lifetime <= time::Duration::zero();
+    }
+
+    if expired {
+        game.power_up = None;
+    }
+}
+
+// Pulls every edible food one cell towards the snake's head
+fn attract_food(game: &mut Game) {
+    let head: Coordinates = game.snake.segments.first().unwrap().clone();
+
+    for i in 0..game.foods.len() {
+        if game.foods[i].kind == FoodKind::Poison {
+            continue;
+        }
+
+        let food_position: Coordinates = game.foods[i].position.clone();
+        let dx: i32 = head.x - food_position.x;
+        let dy: i32 = head.y - food_position.y;
+
+        let mut target: Coordinates = food_position.clone();
+        if dx.abs() >= dy.abs() {
+            target.x += dx.signum();
+        } else {
+            target.y += dy.signum();
+        }
+
+        // The food may move onto the head (and be eaten), but never into the body, an obstacle or other food
+        let is_blocked: bool = game.obstacles.contains(&target)
+            || game.snake.segments.contains(&target)
+            || level::portal_exit(&game.portals, &target).is_some()
+            || game.foods.iter().any(|food| food.position == target);
+
+        if (target == head) || !is_blocked {
+            game.foods[i].position = target;
+        }
+    }
+}
+
+fn effective_move_frequency(game: &Game) -> Duration {
+    let mut move_frequency: Duration = game.snake.move_frequency;
+
+    if is_effect_active(game, PowerUpKind::SlowMotion) {
+        move_frequency = move_frequency * 2;
+    }
+
+    if is_effect_active(game, PowerUpKind::Speed) {
+        move_frequency = move_frequency / 2;
+    }
+
+    // Boosting only changes the effective frequency, so calc_move_frequency keeps working from the score
+    if game.is_boosting {
+        move_frequency = move_frequency / 2;
+    }
+
+    move_frequency
 }
 
-fn reset_snake(snake: &mut Snake) {
+fn reset_snake(snake: &mut Snake, position: Coordinates) {
     snake.segments.clear();
-    snake.segments.push(Coordinates::new(15, 8));
+    snake.segments.push(position);
+    snake.births.clear();
+    snake.births.push(0);
+    snake.moves = 0;
+    snake.direction = Direction::None;
+    snake.pending_growth = 0;
 }
 
 fn reset_game(game: &mut Game) {
-    reset_snake(&mut game.snake);
+    let level: &Level = current_level(game);
+
+    game.obstacles = level::build_obstacles(level);
+    game.portals = level::build_portals(level);
+    reset_snake(&mut game.snake, Coordinates::new(level.start_x, level.start_y));
+    game.snake.move_frequency = time::Duration::milliseconds(level.move_frequency_ms);
     reset_food(game);
     game.score = 0;
+    game.food_collected = 0;
+    game.level_time = time::Duration::zero();
+    game.power_up = None;
+    game.effects.clear();
+    game.history.clear();
+    game.rewind_charges = REWIND_CHARGES;
+    game.is_rewinding = false;
+    game.death = None;
+    game.particles.clear();
+    game.is_boosting = false;
+    game.boost_meter = time::Duration::milliseconds(BOOST_METER_MS);
+    game.boost_cost_timer = time::Duration::zero();
     game.state = GameState::Playing;
 }
 
-fn collect_food(game: &mut Game) {
+fn start_game(game: &mut Game, mode: GameMode, level_index: usize) {
+    delete_save(game);
+    game.mode = mode;
+    game.level_index = level_index;
+    reset_game(game);
+}
+
+fn collect_food(game: &mut Game, index: usize) {
+    let food: Food = game.foods.remove(index);
+    let kind: FoodKind = food.kind;
+    particle::spawn_burst(&mut game.particles, &food.position, food_sprite(kind).color);
+
+    if kind == FoodKind::Poison {
+        shrink_snake(&mut game.snake, POISON_SHRINK_SEGMENTS);
+        return;
+    }
+
     calc_move_frequency(game);
-    reset_food(game);
-    grow_snake(&mut game.snake);
-    game.score += 1;
+
+    let mut value: u32 = food_value(kind);
+    if is_effect_active(game, PowerUpKind::ScoreMultiplier) {
+        value *= SCORE_MULTIPLIER;
+    }
+
+    game.score += value;
+    let text_position: Coordinates = Coordinates::new(food.position.x, food.position.y + 1);
+    particle::spawn_text(&mut game.particles, &text_position, &format!("+{}", value), COLOR_YELLOW);
+
+    if kind == FoodKind::Speed {
+        add_effect(game, PowerUpKind::Speed);
+    }
+
+    game.food_collected += 1;
+    let growth: u32 = food_growth(kind) * current_level(game).growth_per_food;
+    grow_snake(&mut game.snake, growth);
+
+    if kind == FoodKind::Normal {
+        let next_kind: FoodKind = random_food_kind(&mut game.rng);
+        spawn_food(game, next_kind);
+        spawn_power_up(game);
+    }
+
+    ensure_food(game);
 }
 
 fn calc_move_frequency(game: &mut Game) {
-    let mut move_frequency_ms: i64 = BASE_MOVE_FREQUENCY_MS - f32::powf(game.score as f32, 1.4) as i64;
+    let base_move_frequency_ms: i64 = current_level(game).move_frequency_ms;
+    let mut move_frequency_ms: i64 = base_move_frequency_ms - f32::powf(game.score as f32, 1.4) as i64;
     move_frequency_ms = move_frequency_ms.max(MAX_MOVE_FREQUENCY_MS);
     game.snake.move_frequency = time::Duration::milliseconds(move_frequency_ms);
 }
 
-fn game_over(game: &mut Game) {
-    game.food_position = None;
-    game.snake.move_frequency = time::Duration::milliseconds(BASE_MOVE_FREQUENCY_MS);
+fn game_over(game: &mut Game, cause: DeathCause, position: Coordinates) {
+    delete_save(game);
+    particle::spawn_burst(&mut game.particles, &position, COLOR_RED);
+    game.death = Some(Death::new(cause, position));
+    game.foods.clear();
+    game.power_up = None;
+    game.effects.clear();
+    game.snake.move_frequency = time::Duration::milliseconds(current_level(game).move_frequency_ms);
     game.snake.direction = Direction::None;
     game.state = GameState::GameOver;
 }
 
+fn complete_level(game: &mut Game) {
+    delete_save(game);
+    game.foods.clear();
+    game.power_up = None;
+    game.effects.clear();
+    game.snake.direction = Direction::None;
+    game.state = GameState::LevelComplete;
+
+    let next_level_index: usize = game.level_index + 1;
+    if (next_level_index < LEVELS.len()) && (next_level_index >= game.unlocked_levels) {
+        game.unlocked_levels = next_level_index + 1;
+        level::save_progress(game.unlocked_levels);
+    }
+}
+
+fn check_goal(game: &mut Game) {
+    if game.mode != GameMode::Campaign {
+        return;
+    }
+
+    let elapsed_seconds: i64 = game.level_time.num_seconds();
+    match current_level(game).goal {
+        Goal::None => {},
+        Goal::ReachLength(length) => {
+            if game.snake.segments.len() >= length {
+                complete_level(game);
+            }
+        },
+        Goal::CollectFood { count, seconds } => {
+            if game.food_collected >= count {
+                complete_level(game);
+            } else if elapsed_seconds >= seconds {
+                let head: Coordinates = game.snake.segments.first().unwrap().clone();
+                game_over(game, DeathCause::TimeUp, head);
+            }
+        },
+        Goal::Survive(seconds) => {
+            if elapsed_seconds >= seconds {
+                complete_level(game);
+            }
+        }
+    }
+}
+
 fn handle_collision(game: &mut Game) {
     let head: Coordinates = game.snake.segments.first().unwrap().clone();
 
     // Segment collisions
-    if game.snake.has_moved && (game.snake.direction != Direction::None) {
+    let is_ghost: bool = is_effect_active(game, PowerUpKind::Ghost);
+    if game.snake.has_moved && (game.snake.direction != Direction::None) && !is_ghost {
         let segments_cloned: Vec<Coordinates> = game.snake.segments.clone();
         for i in 1..segments_cloned.len() {
             let segment: &Coordinates = &segments_cloned[i];
             if head == *segment {
-                game_over(game);
+                game_over(game, DeathCause::BitSelf, head);
+                return;
             }
         }
     }
 
     // Wall collisions
     if head.x <= 0 || head.x >= 28 || head.y <= 0 || head.y >= 13 {
-        game_over(game);
+        game_over(game, DeathCause::HitWall, head);
+        return;
+    }
+
+    // Obstacle collisions
+    if game.obstacles.contains(&head) {
+        game_over(game, DeathCause::HitObstacle, head);
+        return;
     }
 
     // Food collision
-    if let Some(food_position) = game.food_position.clone() {
-        if head == food_position {
-            collect_food(game);
+    if let Some(index) = game.foods.iter().position(|food| food.position == head) {
+        collect_food(game, index);
+    }
+
+    // Power-up collision
+    let mut collected_kind: Option<PowerUpKind> = None;
+    if let Some(ref power_up) = game.power_up {
+        if head == power_up.position {
+            collected_kind = Some(power_up.kind);
         }
     }
+
+    if let Some(kind) = collected_kind {
+        collect_power_up(game, kind);
+    }
+}
+
+fn delete_save(game: &mut Game) {
+    save::delete_save();
+    game.has_save = false;
+}
+
+fn open_menu(game: &mut Game) {
+    game.menu_selection = 0;
+    game.state = GameState::Menu;
 }
 
-fn update(input_man: &InputMan, game: &mut Game) {
-    if game.state == GameState::Playing {
+fn update(input_man: &InputMan, game: &mut Game, delta_time: Duration) {
+    particle::update_particles(&mut game.particles, delta_time);
+
+    if is_key_pressed(input_man, VirtualKeyCode::T) {
+        game.theme_index = (game.theme_index + 1) % game.themes.len();
+    }
+
+    if (game.state == GameState::Playing) || (game.state == GameState::GameOver) {
+        if rewind::update_rewind(input_man, game, delta_time) {
+            return;
+        }
+    }
+
+    if game.state == GameState::Menu {
+        update_menu(input_man, game);
+    } else if game.state == GameState::Playing {
+        if is_key_pressed(input_man, VirtualKeyCode::Escape) {
+            save::save_game(game);
+            game.has_save = true;
+            open_menu(game);
+            return;
+        }
+
+        if game.snake.direction != Direction::None {
+            game.level_time = game.level_time + delta_time;
+            update_effects(game, delta_time);
+            update_foods(game, delta_time);
+            ensure_food(game);
+        }
+
+        boost::update_boost(input_man, game, delta_time);
         update_snake(input_man, game);
+        if game.snake.has_moved && is_effect_active(game, PowerUpKind::Magnet) {
+            attract_food(game);
+        }
+
+        let is_fast: bool = effective_move_frequency(game) <= time::Duration::milliseconds(SPARKLE_MOVE_FREQUENCY_MS);
+        if game.snake.has_moved && is_fast {
+            spawn_head_sparkle(game);
+        }
+
         handle_collision(game);
+
+        if game.state == GameState::Playing {
+            check_goal(game);
+        }
+
+        if (game.state == GameState::Playing) && game.snake.has_moved {
+            let snapshot: Snapshot = rewind::take_snapshot(game);
+            rewind::record_snapshot(&mut game.history, snapshot);
+        }
     } else if game.state == GameState::GameOver {
+        if let Some(ref mut death) = game.death {
+            death.timer = death.timer + delta_time;
+        }
+
         if is_key_pressed(input_man, VirtualKeyCode::Space) {
             reset_game(game);
+        } else if is_key_pressed(input_man, VirtualKeyCode::Escape) {
+            open_menu(game);
+        }
+    } else if game.state == GameState::LevelComplete {
+        if is_key_pressed(input_man, VirtualKeyCode::Space) {
+            if game.level_index + 1 < LEVELS.len() {
+                let next_level_index: usize = game.level_index + 1;
+                start_game(game, GameMode::Campaign, next_level_index);
+            } else {
+                open_menu(game);
+            }
+        } else if is_key_pressed(input_man, VirtualKeyCode::Escape) {
+            open_menu(game);
+        }
+    }
+}
+
+fn menu_items(game: &Game) -> Vec<MenuItem> {
+    let mut items: Vec<MenuItem> = Vec::new();
+
+    if game.has_save {
+        items.push(MenuItem::Continue);
+    }
+
+    items.push(MenuItem::Endless);
+    items.push(MenuItem::Campaign);
+    items.push(MenuItem::Skin);
+    items
+}
+
+fn update_menu(input_man: &InputMan, game: &mut Game) {
+    // The help screen covers the menu until it is closed
+    if game.is_help_open {
+        if is_key_pressed(input_man, HELP_KEY) || is_key_pressed(input_man, VirtualKeyCode::Escape) || is_key_pressed(input_man, VirtualKeyCode::Space) {
+            game.is_help_open = false;
+        }
+
+        return;
+    }
+
+    if is_key_pressed(input_man, HELP_KEY) {
+        game.is_help_open = true;
+        return;
+    }
+
+    let items: Vec<MenuItem> = menu_items(game);
+
+    if is_key_pressed(input_man, VirtualKeyCode::W) && game.menu_selection > 0 {
+        game.menu_selection -= 1;
+    } else if is_key_pressed(input_man, VirtualKeyCode::S) && game.menu_selection + 1 < items.len() {
+        game.menu_selection += 1;
+    }
+
+    let item: MenuItem = items[game.menu_selection];
+
+    // Level select
+    if item == MenuItem::Campaign {
+        if is_key_pressed(input_man, VirtualKeyCode::A) && game.level_index > 0 {
+            game.level_index -= 1;
+        } else if is_key_pressed(input_man, VirtualKeyCode::D) && game.level_index + 1 < game.unlocked_levels {
+            game.level_index += 1;
+        }
+    }
+
+    // Skin select
+    if item == MenuItem::Skin {
+        if is_key_pressed(input_man, VirtualKeyCode::A) {
+            game.skin_index = (game.skin_index + SKINS.len() - 1) % SKINS.len();
+        } else if is_key_pressed(input_man, VirtualKeyCode::D) {
+            game.skin_index = (game.skin_index + 1) % SKINS.len();
         }
     }
+
+    if is_key_pressed(input_man, VirtualKeyCode::Space) {
+        match item {
+            MenuItem::Continue => {
+                if !save::load_game(game) {
+                    delete_save(game);
+                    open_menu(game);
+                }
+            },
+            MenuItem::Endless => { start_game(game, GameMode::Endless, 0); },
+            MenuItem::Campaign => {
+                let level_index: usize = game.level_index;
+                start_game(game, GameMode::Campaign, level_index);
+            },
+            MenuItem::Skin => {}
+        }
+    }
+}
+
+fn current_theme(game: &Game) -> &Theme {
+    &game.themes[game.theme_index]
 }
 
 fn render(renderer: &mut Renderer, game: &mut Game) {
-    render_snake(renderer, &game.snake);
+    let theme: &Theme = current_theme(game);
+    renderer.text_color = theme.text;
+    renderer.border_color = theme.border;
+    renderer.background_color = theme.background;
+
+    if game.state == GameState::Menu {
+        renderer.layer = Layer::Modal;
+        render_menu(renderer, game);
+        return;
+    }
+
+    // Render obstacles
+    renderer.layer = Layer::Background;
+    let obstacle_sprite: Sprite = Sprite::new(SPRITE_OBSTACLE.graphic, theme.wall);
+    for obstacle in &game.obstacles {
+        gfx::draw_cell(renderer, obstacle.x, obstacle.y, obstacle_sprite);
+    }
+
+    // Render portals
+    for portal in &game.portals {
+        let color: Color = PORTAL_COLORS[portal.id as usize % PORTAL_COLORS.len()];
+        let floor: Color = gfx::lerp_color(color, theme.background, 0.75);
+        let sprite: Sprite = Sprite::with_background(SPRITE_PORTAL.graphic, color, floor);
+        gfx::draw_cell(renderer, portal.a.x, portal.a.y, sprite);
+        gfx::draw_cell(renderer, portal.b.x, portal.b.y, sprite);
+    }
+
+    renderer.layer = Layer::World;
+
+    // Rewinding and active effects tint the whole snake, overriding the skin
+    let mut override_color: Option<Color> = if game.is_rewinding {
+        Some(COLOR_BLUE)
+    } else {
+        game.effects.last().map(|effect| effect_color(effect.kind))
+    };
+
+    // Dying snakes flash red and then dissolve from the tail
+    let mut visible_segments: usize = game.snake.segments.len();
+    if let Some(ref death) = game.death {
+        if death.is_flash_on() {
+            override_color = Some(COLOR_RED);
+        }
+
+        visible_segments = visible_segments.saturating_sub(death.dissolved_segments());
+    }
+
+    let mut segment_colors: Vec<Color> = match override_color {
+        Some(color) => vec![color; game.snake.segments.len()],
+        None => skin::segment_colors(SKINS[game.skin_index], theme, &game.snake, time::precise_time_s() as f32)
+    };
+
+    // Ghosts are see-through
+    if is_effect_active(game, PowerUpKind::Ghost) && !game.is_rewinding {
+        for color in &mut segment_colors {
+            *color = gfx::color_with_alpha(*color, GHOST_ALPHA);
+        }
+    }
+
+    render_snake(renderer, &game.snake.segments[..visible_segments], &game.snake.direction, &segment_colors);
+
+    // Render power-up
+    if let Some(ref power_up) = game.power_up {
+        gfx::draw_cell(renderer, power_up.position.x, power_up.position.y, powerup_sprite(power_up.kind));
+    }
 
     // Render food
-    if let Some(ref food_position) = game.food_position {
-        gfx::draw_cell(renderer, food_position.x, food_position.y, SPRITE_FOOD);
+    for food in &game.foods {
+        let mut sprite: Sprite = food_sprite(food.kind);
+        if food.kind == FoodKind::Normal {
+            sprite.color = theme.food;
+        }
+
+        gfx::draw_cell(renderer, food.position.x, food.position.y, sprite);
     }
 
+    renderer.layer = Layer::Effects;
+    particle::render_particles(renderer, &game.particles);
+
+    renderer.layer = Layer::Ui;
+
     // Render score text
     gfx::draw_string(renderer, 1, 14, &format!("SCORE: {}", game.score));
 
+    // Render goal text
+    if game.mode == GameMode::Campaign {
+        gfx::draw_string(renderer, 12, 14, &goal_text(game));
+    }
+
+    // Render active effects, leaving room for the boost meter
+    let mut x: i32 = 1;
+    for effect in &game.effects {
+        let text: String = format!("{}:{}", effect_name(effect.kind), effect.remaining.num_seconds() + 1);
+        if x + text.len() as i32 > 20 {
+            break;
+        }
+
+        gfx::draw_string(renderer, x, 15, &text);
+        x += text.len() as i32 + 1;
+    }
+
+    // Render boost meter
+    boost::render_boost_meter(renderer, game, 20, 15);
+
+    // Render rewind charges
+    gfx::draw_string(renderer, 27, 14, &format!("«{}", game.rewind_charges));
+
+    // Render pending growth
+    if game.snake.pending_growth > 0 {
+        gfx::draw_string(renderer, 27, 15, &format!("↑{}", game.snake.pending_growth));
+    }
+
     // Render main window border
     gfx::draw_box(renderer, 0, 0, 29, 14);
 
-    if game.state == GameState::GameOver {
-        gfx::draw_string(renderer, 1, 1, "Press SPACE to play again.");
+    // Messages go on the modal layer, on a panel that dims the playfield behind them
+    renderer.layer = Layer::Modal;
+    if (game.state == GameState::GameOver || game.state == GameState::LevelComplete) && !game.is_rewinding {
+        gfx::draw_background(renderer, 1, 1, 27, 3, gfx::color_with_alpha(theme.background, PANEL_ALPHA));
+    }
+
+    // Render collision cell, above the panel so a death beneath it can still be seen
+    if let Some(ref death) = game.death {
+        if death.is_flash_on() {
+            let sprite: Sprite = Sprite::with_background(SPRITE_DEATH.graphic, COLOR_WHITE, SPRITE_DEATH.color);
+            gfx::draw_cell(renderer, death.position.x, death.position.y, sprite);
+        }
+    }
+
+    if game.is_rewinding {
+        gfx::draw_string(renderer, 1, 1, "« REWINDING");
+    } else if game.state == GameState::GameOver {
+        let message: &str = game.death.as_ref().map_or("", |death| death_message(death.cause));
+        let hint: &str = if (game.rewind_charges > 0) && !game.history.is_empty() { "Hold BACKSPACE to rewind." } else { "" };
+        text::draw_text(renderer, 1, 1, 27, 3, &format!("{}\n{}\nPress SPACE to play again.", message, hint), Align::Left);
+    } else if game.state == GameState::LevelComplete {
+        if game.level_index + 1 < LEVELS.len() {
+            gfx::draw_string(renderer, 1, 2, "Level complete!");
+            gfx::draw_string(renderer, 1, 1, "Press SPACE to continue.");
+        } else {
+            gfx::draw_string(renderer, 1, 2, "Campaign complete!");
+            gfx::draw_string(renderer, 1, 1, "Press SPACE for the menu.");
+        }
     } else if game.snake.direction == Direction::None {
+        let panel_height: u32 = if game.mode == GameMode::Campaign { 2 } else { 1 };
+        gfx::draw_background(renderer, 1, 1, 27, panel_height, theme.background);
+
+        if game.mode == GameMode::Campaign {
+            let level: &Level = current_level(game);
+            gfx::draw_string(renderer, 1, 2, &format!("{}: {}", game.level_index + 1, level.name));
+        }
+
         gfx::draw_string(renderer, 1, 1, "Use the WASD keys to move.");
     }
 }
 
+fn goal_text(game: &Game) -> String {
+    let elapsed_seconds: i64 = game.level_time.num_seconds();
+    match current_level(game).goal {
+        Goal::None => String::new(),
+        Goal::ReachLength(length) => format!("LENGTH: {}/{}", game.snake.segments.len(), length),
+        Goal::CollectFood { count, seconds } => {
+            format!("FOOD: {}/{} {}s", game.food_collected, count, (seconds - elapsed_seconds).max(0))
+        },
+        Goal::Survive(seconds) => format!("SURVIVE: {}s", (seconds - elapsed_seconds).max(0))
+    }
+}
+
+fn render_menu(renderer: &mut Renderer, game: &Game) {
+    gfx::draw_box(renderer, 0, 0, 29, 14);
+    text::draw_aligned(renderer, 1, 12, 27, "H: Help", Align::Right);
+    text::draw_aligned(renderer, 1, 11, 27, "ROSTIGE SCHLANGE", Align::Center);
+
+    for (i, item) in menu_items(game).iter().enumerate() {
+        let text: String = match *item {
+            MenuItem::Continue => String::from("Continue"),
+            MenuItem::Endless => String::from("Endless"),
+            MenuItem::Campaign => format!("Campaign < Level {} >", game.level_index + 1),
+            MenuItem::Skin => format!("Skin < {} >", skin_name(SKINS[game.skin_index]))
+        };
+
+        let y: i32 = 8 - i as i32;
+        if i == game.menu_selection {
+            gfx::draw_string(renderer, 3, y, ">");
+        }
+
+        gfx::draw_string(renderer, 5, y, &text);
+
+        if i == game.menu_selection {
+            let theme: &Theme = current_theme(game);
+            let bar_color: Color = gfx::lerp_color(theme.border, theme.background, 0.7);
+            gfx::draw_background(renderer, 2, y, 25, 1, bar_color);
+        }
+    }
+
+    gfx::draw_string(renderer, 1, 4, &format!("Theme: {} (T)", current_theme(game).name));
+    let font_text: String = format!("Font: {} (F)", renderer.font.name);
+    gfx::draw_string(renderer, 1, 3, &font_text);
+    gfx::draw_string(renderer, 1, 2, "W/S to select, A/D change.");
+    gfx::draw_string(renderer, 1, 1, "Press SPACE to start.");
+
+    if game.is_help_open {
+        text::draw_text_box(renderer, 1, 12, 27, HELP_TEXT, Align::Left);
+    }
+}
+
 fn update_snake(input_man: &InputMan, game: &mut Game) {
+    let move_frequency: Duration = effective_move_frequency(game);
+    let portals: &Vec<Portal> = &game.portals;
     let snake: &mut Snake = &mut game.snake;
 
     // Input
@@ -275,10 +1207,13 @@ fn update_snake(input_man: &InputMan, game: &mut Game) {
 
     // Movement
     let current_time: Tm = time::now();
-    if (current_time - snake.last_move_time) > snake.move_frequency {
+    if (current_time - snake.last_move_time) > move_frequency {
         snake.last_move_time = current_time;
 
         if snake.direction != Direction::None {
+            let tail: Coordinates = snake.segments.last().unwrap().clone();
+            snake.moves += 1;
+
             // Update segment positions in reverse order (from tail to head)
             let segments_cloned: Vec<Coordinates> = snake.segments.clone();
             for i in (1..snake.segments.len()).rev() {
@@ -288,15 +1223,24 @@ fn update_snake(input_man: &InputMan, game: &mut Game) {
                 segment.x = next_segment.x;
                 segment.y = next_segment.y;
             }
+
+            // Growing leaves a new segment where the tail used to be
+            if snake.pending_growth > 0 {
+                snake.segments.push(tail);
+                snake.births.push(snake.moves);
+                snake.pending_growth -= 1;
+            }
         }
 
         // Update head position
-        match snake.direction {
-            Direction::North => { snake.segments.first_mut().unwrap().y += 1 },
-            Direction::South => { snake.segments.first_mut().unwrap().y -= 1 },
-            Direction::East => { snake.segments.first_mut().unwrap().x += 1 },
-            Direction::West => { snake.segments.first_mut().unwrap().x -= 1 }
-            Direction::None => {}
+        let head: &mut Coordinates = snake.segments.first_mut().unwrap();
+        move_coordinates(head, &snake.direction);
+
+        // Entering a portal places the head one step past its partner, keeping the direction.
+        // The body follows through because each segment takes the previous position of the one ahead of it.
+        if let Some(exit) = level::portal_exit(portals, head) {
+            *head = exit;
+            move_coordinates(head, &snake.direction);
         }
 
         snake.has_moved = true;
@@ -305,8 +1249,118 @@ fn update_snake(input_man: &InputMan, game: &mut Game) {
     }
 }
 
-fn render_snake(renderer: &mut Renderer, snake: &Snake) {
-    for segment in &snake.segments {
-        gfx::draw_cell(renderer, segment.x, segment.y, SPRITE_SNAKE);
+// Leaves a sparkle beside the cell the head just left
+fn spawn_head_sparkle(game: &mut Game) {
+    let head: Coordinates = game.snake.segments.first().unwrap().clone();
+    let side: i32 = if rand::thread_rng().gen() { 1 } else { -1 };
+
+    let position: Coordinates = match game.snake.direction {
+        Direction::North => Coordinates::new(head.x + side, head.y - 1),
+        Direction::South => Coordinates::new(head.x + side, head.y + 1),
+        Direction::East => Coordinates::new(head.x - 1, head.y + side),
+        Direction::West => Coordinates::new(head.x + 1, head.y + side),
+        Direction::None => { return; }
+    };
+
+    particle::spawn_sparkle(&mut game.particles, &position, COLOR_YELLOW);
+}
+
+fn move_coordinates(position: &mut Coordinates, direction: &Direction) {
+    match *direction {
+        Direction::North => { position.y += 1 },
+        Direction::South => { position.y -= 1 },
+        Direction::East => { position.x += 1 },
+        Direction::West => { position.x -= 1 }
+        Direction::None => {}
+    }
+}
+
+fn render_snake(renderer: &mut Renderer, segments: &[Coordinates], direction: &Direction, colors: &[Color]) {
+    for i in 0..segments.len() {
+        let graphic: char = segment_graphic(segments, i, direction);
+        gfx::draw_cell(renderer, segments[i].x, segments[i].y, Sprite::new(graphic, colors[i]));
+    }
+}
+
+// Picks the glyph for a segment based on the neighbouring segments it connects to
+fn segment_graphic(segments: &[Coordinates], index: usize, direction: &Direction) -> char {
+    let towards_head: Option<Direction> = if index > 0 {
+        adjacent_direction(&segments[index], &segments[index - 1])
+    } else {
+        None
+    };
+
+    let towards_tail: Option<Direction> = if index + 1 < segments.len() {
+        adjacent_direction(&segments[index], &segments[index + 1])
+    } else {
+        None
+    };
+
+    // Head
+    if index == 0 {
+        let facing: Direction = match (*direction, towards_tail) {
+            (Direction::None, Some(neck)) => opposite_direction(neck),
+            (current, _) => current
+        };
+
+        return match facing {
+            Direction::North => '▲',
+            Direction::South => '▼',
+            Direction::East => '►',
+            Direction::West => '◄',
+            Direction::None => SPRITE_SNAKE.graphic
+        };
+    }
+
+    // Tail
+    if index + 1 == segments.len() {
+        return '•';
+    }
+
+    // Body. Segments separated by a portal, or stacked while growing, only connect on one side.
+    match (towards_head, towards_tail) {
+        (Some(a), Some(b)) => body_graphic(a, b),
+        (Some(a), None) | (None, Some(a)) => body_graphic(a, opposite_direction(a)),
+        (None, None) => SPRITE_SNAKE.graphic
+    }
+}
+
+fn body_graphic(a: Direction, b: Direction) -> char {
+    let connects = |direction: Direction| (a == direction) || (b == direction);
+
+    if connects(Direction::North) && connects(Direction::South) {
+        '║' // Vertical
+    } else if connects(Direction::East) && connects(Direction::West) {
+        '═' // Horizontal
+    } else if connects(Direction::North) && connects(Direction::East) {
+        '╚'
+    } else if connects(Direction::North) && connects(Direction::West) {
+        '╝'
+    } else if connects(Direction::South) && connects(Direction::East) {
+        '╔'
+    } else if connects(Direction::South) && connects(Direction::West) {
+        '╗'
+    } else {
+        SPRITE_SNAKE.graphic
+    }
+}
+
+fn adjacent_direction(from: &Coordinates, to: &Coordinates) -> Option<Direction> {
+    match (to.x - from.x, to.y - from.y) {
+        (0, 1) => Some(Direction::North),
+        (0, -1) => Some(Direction::South),
+        (1, 0) => Some(Direction::East),
+        (-1, 0) => Some(Direction::West),
+        _ => None
+    }
+}
+
+fn opposite_direction(direction: Direction) -> Direction {
+    match direction {
+        Direction::North => Direction::South,
+        Direction::South => Direction::North,
+        Direction::East => Direction::West,
+        Direction::West => Direction::East,
+        Direction::None => Direction::None
     }
 }
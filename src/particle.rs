@@ -0,0 +1,93 @@
+use std::f32::consts::PI;
+use time::Duration;
+use ::*;
+
+pub const BURST_PARTICLE_COUNT: u32 = 8;
+pub const BURST_LIFETIME_MS: i64 = 400;
+pub const SPARKLE_LIFETIME_MS: i64 = 300;
+pub const TEXT_LIFETIME_MS: i64 = 700;
+
+// Moves at or below this frequency leave sparkles behind the head
+pub const SPARKLE_MOVE_FREQUENCY_MS: i64 = 60;
+
+// A glyph that drifts across the cell grid while it fades out
+#[derive(Clone)]
+pub struct Particle {
+    pub x: f32,
+    pub y: f32,
+    pub velocity_x: f32, // Cells per second
+    pub velocity_y: f32,
+    pub graphic: char,
+    pub color: Color,
+    pub lifetime: Duration,
+    pub age: Duration
+}
+
+impl Particle {
+    pub fn new(x: f32, y: f32, graphic: char, color: Color, lifetime: Duration) -> Particle {
+        Particle {
+            x,
+            y,
+            velocity_x: 0.0,
+            velocity_y: 0.0,
+            graphic,
+            color,
+            lifetime,
+            age: time::Duration::zero()
+        }
+    }
+}
+
+// Particles are purely cosmetic, so they use thread_rng and leave the game's RNG untouched
+pub fn spawn_burst(particles: &mut Vec<Particle>, position: &Coordinates, color: Color) {
+    let mut rng = rand::thread_rng();
+
+    for i in 0..BURST_PARTICLE_COUNT {
+        let angle: f32 = (i as f32 / BURST_PARTICLE_COUNT as f32) * 2.0 * PI;
+        let speed: f32 = rng.gen_range(6.0, 10.0);
+
+        let mut particle: Particle = Particle::new(position.x as f32, position.y as f32, '*', color,
+                                                   time::Duration::milliseconds(BURST_LIFETIME_MS));
+        particle.velocity_x = angle.cos() * speed;
+        particle.velocity_y = angle.sin() * speed;
+        particles.push(particle);
+    }
+}
+
+pub fn spawn_sparkle(particles: &mut Vec<Particle>, position: &Coordinates, color: Color) {
+    let graphic: char = if rand::thread_rng().gen() { '·' } else { '∙' };
+    particles.push(Particle::new(position.x as f32, position.y as f32, graphic, color,
+                                 time::Duration::milliseconds(SPARKLE_LIFETIME_MS)));
+}
+
+// Text that floats upwards and fades, like score popups
+pub fn spawn_text(particles: &mut Vec<Particle>, position: &Coordinates, text: &str, color: Color) {
+    for (i, c) in text.chars().enumerate() {
+        let mut particle: Particle = Particle::new((position.x + i as i32) as f32, position.y as f32, c, color,
+                                                   time::Duration::milliseconds(TEXT_LIFETIME_MS));
+        particle.velocity_y = 2.0;
+        particles.push(particle);
+    }
+}
+
+pub fn update_particles(particles: &mut Vec<Particle>, delta_time: Duration) {
+    let delta_seconds: f32 = delta_time.num_milliseconds() as f32 / 1000.0;
+
+    for particle in particles.iter_mut() {
+        particle.age = particle.age + delta_time;
+        particle.x += particle.velocity_x * delta_seconds;
+        particle.y += particle.velocity_y * delta_seconds;
+    }
+
+    particles.retain(|particle| particle.age < particle.lifetime);
+}
+
+pub fn render_particles(renderer: &mut Renderer, particles: &[Particle]) {
+    for particle in particles {
+        let t: f32 = particle.age.num_milliseconds() as f32 / particle.lifetime.num_milliseconds() as f32;
+        let color: Color = gfx::color_with_alpha(particle.color, 1.0 - t);
+        let sprite: Sprite = Sprite::new(particle.graphic, color);
+
+        gfx::draw_cell(renderer, particle.x.round() as i32, particle.y.round() as i32, sprite);
+    }
+}
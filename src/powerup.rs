@@ -0,0 +1,104 @@
+use time::Duration;
+use ::*;
+
+pub const POWERUP_SPAWN_CHANCE: u32 = 4; // 1 in N chance per food collected
+pub const POWERUP_LIFETIME_MS: i64 = 8000;
+pub const SHRINK_SEGMENTS: usize = 3;
+pub const SCORE_MULTIPLIER: u32 = 2;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum PowerUpKind {
+    Ghost,
+    SlowMotion,
+    ScoreMultiplier,
+    Shrink,
+    Magnet,
+    Speed // Only granted by speed food, never spawned as a power-up
+}
+
+pub const POWERUP_KINDS: [PowerUpKind; 5] = [
+    PowerUpKind::Ghost,
+    PowerUpKind::SlowMotion,
+    PowerUpKind::ScoreMultiplier,
+    PowerUpKind::Shrink,
+    PowerUpKind::Magnet
+];
+
+#[derive(Clone)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub position: Coordinates,
+    pub lifetime: Duration
+}
+
+impl PowerUp {
+    pub fn new(kind: PowerUpKind, position: Coordinates) -> PowerUp {
+        PowerUp {
+            kind,
+            position,
+            lifetime: time::Duration::milliseconds(POWERUP_LIFETIME_MS)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ActiveEffect {
+    pub kind: PowerUpKind,
+    pub remaining: Duration
+}
+
+impl ActiveEffect {
+    pub fn new(kind: PowerUpKind) -> ActiveEffect {
+        ActiveEffect {
+            kind,
+            remaining: effect_duration(kind)
+        }
+    }
+}
+
+// Instant power-ups (like shrink) have a zero duration and are never added to the active effects.
+pub fn effect_duration(kind: PowerUpKind) -> Duration {
+    match kind {
+        PowerUpKind::Ghost => time::Duration::seconds(8),
+        PowerUpKind::SlowMotion => time::Duration::seconds(6),
+        PowerUpKind::ScoreMultiplier => time::Duration::seconds(10),
+        PowerUpKind::Shrink => time::Duration::zero(),
+        PowerUpKind::Magnet => time::Duration::seconds(8),
+        PowerUpKind::Speed => time::Duration::seconds(5)
+    }
+}
+
+pub fn effect_name(kind: PowerUpKind) -> &'static str {
+    match kind {
+        PowerUpKind::Ghost => "GHOST",
+        PowerUpKind::SlowMotion => "SLOW",
+        PowerUpKind::ScoreMultiplier => "X2",
+        PowerUpKind::Shrink => "SHRINK",
+        PowerUpKind::Magnet => "MAGNET",
+        PowerUpKind::Speed => "FAST"
+    }
+}
+
+pub fn effect_color(kind: PowerUpKind) -> Color {
+    match kind {
+        PowerUpKind::Ghost => COLOR_GRAY,
+        PowerUpKind::SlowMotion => COLOR_CYAN,
+        PowerUpKind::ScoreMultiplier => COLOR_YELLOW,
+        PowerUpKind::Shrink => COLOR_MAGENTA,
+        PowerUpKind::Magnet => COLOR_RED,
+        PowerUpKind::Speed => COLOR_ORANGE
+    }
+}
+
+pub fn powerup_sprite(kind: PowerUpKind) -> Sprite {
+    let graphic: char = match kind {
+        PowerUpKind::Ghost => 'Ω',
+        PowerUpKind::SlowMotion => '∞',
+        PowerUpKind::ScoreMultiplier => '☼',
+        PowerUpKind::Shrink => '↓',
+        PowerUpKind::Magnet => '♂',
+        PowerUpKind::Speed => SPRITE_FOOD_SPEED.graphic
+    };
+
+    Sprite::new(graphic, effect_color(kind))
+}
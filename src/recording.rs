@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::Path;
+use gif::SetParameter;
+use image::RgbaImage;
+use ::*;
+
+pub const RECORD_KEY: VirtualKeyCode = VirtualKeyCode::F9;
+pub const RECORDINGS_PATH: &'static str = "recordings";
+
+// A frame is captured on every sim tick, except that GIF players slow down frames shorter than 20ms,
+// so ticks that come sooner than that after the last capture are skipped. Delays are taken from the
+// time that actually passed, so the GIF plays at the game's speed either way.
+pub const MIN_FRAME_MS: i64 = 20;
+pub const MAX_RECORDING_FRAMES: usize = 3000;
+
+// Fades and translucent glyphs end up between a color and the background, so the palette holds steps
+// of each blend. The color wheel for rainbow skins gets fewer steps so everything fits in 256 entries.
+const PALETTE_FADE_STEPS: u32 = 8;
+const PALETTE_HUES: u32 = 24;
+const PALETTE_HUE_FADE_STEPS: u32 = 4;
+
+// Longest delay a GIF frame can hold, in hundredths of a second
+const MAX_FRAME_DELAY: i64 = 65535;
+
+// Frames are encoded as they are captured. The newest one is held back until the next different
+// frame shows up, since only then is it known how long it stayed on screen.
+pub struct Recording {
+    path: String,
+    width: u32,
+    height: u32,
+    palette: Vec<[u8; 3]>,
+    palette_indices: HashMap<[u8; 3], u8>, // Closest palette entry for every color seen so far
+    encoder: gif::Encoder<BufWriter<File>>,
+    pending_frame: Option<Vec<u8>>, // Palette indices
+    pending_time: Duration, // How long the pending frame has been on screen
+    delay_remainder_ms: i64, // Milliseconds lost to rounding delays to hundredths of a second
+    capture_timer: Duration,
+    frame_count: usize
+}
+
+impl Recording {
+    pub fn new(theme: &Theme, renderer: &Renderer, path: &str) -> io::Result<Recording> {
+        let width: u32 = renderer.cols * renderer.cell_width;
+        let height: u32 = renderer.rows * renderer.cell_height;
+        let palette: Vec<[u8; 3]> = theme_palette(theme);
+
+        if let Some(directory) = Path::new(path).parent() {
+            fs::create_dir_all(directory)?;
+        }
+
+        let palette_bytes: Vec<u8> = palette.iter().flat_map(|color| color.iter().cloned()).collect();
+        let file: BufWriter<File> = BufWriter::new(File::create(path)?);
+        let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &palette_bytes)?;
+        encoder.set(gif::Repeat::Infinite)?;
+
+        Ok(Recording {
+            path: String::from(path),
+            width,
+            height,
+            palette,
+            palette_indices: HashMap::new(),
+            encoder,
+            pending_frame: None,
+            pending_time: time::Duration::zero(),
+            delay_remainder_ms: 0,
+            capture_timer: time::Duration::milliseconds(MIN_FRAME_MS),
+            frame_count: 0
+        })
+    }
+}
+
+// Starts and stops recording with the record key. Call after the frame has been rendered.
+pub fn update_recording(recording: &mut Option<Recording>, input_man: &InputMan, theme: &Theme, renderer: &Renderer,
+                        delta_time: Duration) {
+    if input::is_key_pressed(input_man, RECORD_KEY) {
+        match recording.take() {
+            Some(finished) => { finish_recording(finished); },
+            None => {
+                let path: String = screenshot::timestamped_path(RECORDINGS_PATH, "gif");
+                match Recording::new(theme, renderer, &path) {
+                    Ok(started) => {
+                        eprintln!("Recording to {}", path);
+                        *recording = Some(started);
+                    },
+                    Err(e) => { eprintln!("Failed to start recording to {}: {}", path, e); }
+                }
+            }
+        }
+    }
+
+    let is_finished: bool = match *recording {
+        Some(ref mut current) => capture_frame(current, renderer, delta_time),
+        None => false
+    };
+
+    if is_finished {
+        if let Some(finished) = recording.take() {
+            finish_recording(finished);
+        }
+    }
+}
+
+// Returns true once the recording can't go on
+fn capture_frame(recording: &mut Recording, renderer: &Renderer, delta_time: Duration) -> bool {
+    recording.pending_time = recording.pending_time + delta_time;
+    recording.capture_timer = recording.capture_timer + delta_time;
+    if recording.capture_timer < time::Duration::milliseconds(MIN_FRAME_MS) {
+        return false;
+    }
+
+    recording.capture_timer = time::Duration::zero();
+
+    let image: RgbaImage = gfx::capture_frame(renderer);
+    if (image.width() != recording.width) || (image.height() != recording.height) {
+        eprintln!("The frame size changed, stopping the recording");
+        return true;
+    }
+
+    let mut pixels: Vec<u8> = Vec::with_capacity((image.width() * image.height()) as usize);
+    for pixel in image.pixels() {
+        let color: [u8; 3] = [pixel[0], pixel[1], pixel[2]];
+        let index: u8 = match recording.palette_indices.get(&color) {
+            Some(index) => *index,
+            None => closest_color(&recording.palette, color)
+        };
+
+        recording.palette_indices.insert(color, index);
+        pixels.push(index);
+    }
+
+    // Frames where nothing moved just keep the previous one on screen for longer
+    if recording.pending_frame.as_ref() == Some(&pixels) {
+        return false;
+    }
+
+    if let Err(e) = write_pending_frame(recording) {
+        eprintln!("Failed to write recording to {}: {}", recording.path, e);
+        return true;
+    }
+
+    recording.pending_frame = Some(pixels);
+    recording.pending_time = time::Duration::zero();
+
+    if recording.frame_count >= MAX_RECORDING_FRAMES {
+        eprintln!("Recording is full");
+        return true;
+    }
+
+    false
+}
+
+fn write_pending_frame(recording: &mut Recording) -> io::Result<()> {
+    let pixels: Vec<u8> = match recording.pending_frame.take() {
+        Some(pixels) => pixels,
+        None => { return Ok(()); }
+    };
+
+    // GIF delays are in hundredths of a second, so the rounding error is carried into the next frame
+    let shown_ms: i64 = recording.pending_time.num_milliseconds() + recording.delay_remainder_ms;
+    recording.delay_remainder_ms = shown_ms % 10;
+
+    let frame: gif::Frame = gif::Frame {
+        width: recording.width as u16,
+        height: recording.height as u16,
+        delay: (shown_ms / 10).min(MAX_FRAME_DELAY) as u16,
+        buffer: std::borrow::Cow::Owned(pixels),
+        ..gif::Frame::default()
+    };
+
+    recording.encoder.write_frame(&frame)?;
+    recording.frame_count += 1;
+
+    Ok(())
+}
+
+// Writes the last frame. Dropping the encoder ends the file.
+pub fn finish_recording(mut recording: Recording) {
+    match write_pending_frame(&mut recording) {
+        Ok(()) => { eprintln!("Saved recording of {} frames to {}", recording.frame_count, recording.path); },
+        Err(e) => { eprintln!("Failed to write recording to {}: {}", recording.path, e); }
+    }
+}
+
+// The theme's colors and the fixed game colors faded towards the background, plus a color wheel for
+// rainbow skins
+fn theme_palette(theme: &Theme) -> Vec<[u8; 3]> {
+    let colors: [Color; 18] = [
+        theme.background, theme.snake_head, theme.snake_body, theme.food, theme.wall, theme.text, theme.border,
+        COLOR_BLACK, COLOR_WHITE, COLOR_GRAY, COLOR_RED, COLOR_GREEN, COLOR_BLUE, COLOR_YELLOW, COLOR_CYAN,
+        COLOR_MAGENTA, COLOR_ORANGE, COLOR_PURPLE
+    ];
+
+    let mut palette: Vec<[u8; 3]> = Vec::new();
+    for color in colors.iter() {
+        add_fades(&mut palette, theme.background, *color, PALETTE_FADE_STEPS);
+    }
+
+    for i in 0..PALETTE_HUES {
+        let hue: Color = gfx::color_from_hsv(i as f32 * 360.0 / PALETTE_HUES as f32, 0.8, 1.0);
+        add_fades(&mut palette, theme.background, hue, PALETTE_HUE_FADE_STEPS);
+    }
+
+    assert!(palette.len() <= 256, "Recording palette has {} colors", palette.len());
+    palette
+}
+
+fn add_fades(palette: &mut Vec<[u8; 3]>, background: Color, color: Color, steps: u32) {
+    for step in 1..(steps + 1) {
+        let faded: Color = gfx::lerp_color(background, color, step as f32 / steps as f32);
+        let bytes: [u8; 4] = gfx::color_to_bytes(faded);
+        let entry: [u8; 3] = [bytes[0], bytes[1], bytes[2]];
+
+        if !palette.contains(&entry) {
+            palette.push(entry);
+        }
+    }
+}
+
+fn closest_color(palette: &[[u8; 3]], color: [u8; 3]) -> u8 {
+    let distance = |entry: &[u8; 3]| -> i32 {
+        (0..3).map(|i| (entry[i] as i32 - color[i] as i32).pow(2)).sum()
+    };
+
+    let mut closest: usize = 0;
+    for (index, entry) in palette.iter().enumerate() {
+        if distance(entry) < distance(&palette[closest]) {
+            closest = index;
+        }
+    }
+
+    closest as u8
+}
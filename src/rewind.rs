@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+use time::Duration;
+use ::*;
+
+pub const REWIND_CHARGES: u32 = 3;
+pub const REWIND_HISTORY_LENGTH: usize = 40; // Snapshots are taken once per snake move
+pub const REWIND_STEP_MS: i64 = 60;
+
+// Everything needed to put a run back the way it was a few moves ago
+#[derive(Clone)]
+pub struct Snapshot {
+    snake: Snake,
+    foods: Vec<Food>,
+    score: u32,
+    food_collected: u32,
+    level_time: Duration,
+    power_up: Option<PowerUp>,
+    effects: Vec<ActiveEffect>,
+    rng: GameRng,
+    boost_meter: Duration
+}
+
+pub fn take_snapshot(game: &Game) -> Snapshot {
+    Snapshot {
+        snake: game.snake.clone(),
+        foods: game.foods.clone(),
+        score: game.score,
+        food_collected: game.food_collected,
+        level_time: game.level_time,
+        power_up: game.power_up.clone(),
+        effects: game.effects.clone(),
+        rng: game.rng.clone(),
+        boost_meter: game.boost_meter
+    }
+}
+
+pub fn restore_snapshot(game: &mut Game, snapshot: Snapshot) {
+    game.snake = snapshot.snake;
+    game.snake.last_move_time = time::now();
+    game.snake.has_moved = false;
+    game.foods = snapshot.foods;
+    game.score = snapshot.score;
+    game.food_collected = snapshot.food_collected;
+    game.level_time = snapshot.level_time;
+    game.power_up = snapshot.power_up;
+    game.effects = snapshot.effects;
+    game.rng = snapshot.rng;
+    game.boost_meter = snapshot.boost_meter;
+    game.death = None;
+    game.state = GameState::Playing;
+}
+
+pub fn record_snapshot(history: &mut VecDeque<Snapshot>, snapshot: Snapshot) {
+    if history.len() >= REWIND_HISTORY_LENGTH {
+        history.pop_front();
+    }
+
+    history.push_back(snapshot);
+}
+
+// Returns true while the game is rewinding, in which case the regular update is skipped
+pub fn update_rewind(input_man: &InputMan, game: &mut Game, delta_time: Duration) -> bool {
+    if !input::is_key_held(input_man, VirtualKeyCode::Back) {
+        game.is_rewinding = false;
+        return false;
+    }
+
+    if !game.is_rewinding {
+        // While playing, the newest snapshot is the current state
+        let is_playing: bool = game.state == GameState::Playing;
+        let required_snapshots: usize = if is_playing { 2 } else { 1 };
+        if (game.rewind_charges == 0) || (game.history.len() < required_snapshots) {
+            return false;
+        }
+
+        if is_playing {
+            game.history.pop_back();
+        }
+
+        game.rewind_charges -= 1;
+        game.is_rewinding = true;
+        game.rewind_timer = time::Duration::milliseconds(REWIND_STEP_MS);
+    }
+
+    game.rewind_timer = game.rewind_timer + delta_time;
+    if game.rewind_timer >= time::Duration::milliseconds(REWIND_STEP_MS) {
+        game.rewind_timer = time::Duration::zero();
+
+        if let Some(snapshot) = game.history.pop_back() {
+            restore_snapshot(game, snapshot);
+        }
+    }
+
+    true
+}
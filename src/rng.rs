@@ -0,0 +1,39 @@
+use rand::Rng;
+use ::*;
+
+const DEFAULT_SEED: u64 = 0x2545F4914F6CDD1D;
+
+// Xorshift generator used for gameplay randomness. Unlike thread_rng, its state can be saved and restored.
+#[derive(Clone)]
+pub struct GameRng {
+    pub state: u64
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> GameRng {
+        GameRng {
+            state: if seed == 0 { DEFAULT_SEED } else { seed }
+        }
+    }
+
+    pub fn from_time() -> GameRng {
+        let now: Timespec = time::get_time();
+        GameRng::new(((now.sec as u64) << 32) ^ (now.nsec as u64))
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x: u64 = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+
+        x.wrapping_mul(DEFAULT_SEED)
+    }
+}
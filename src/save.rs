@@ -0,0 +1,406 @@
+use std::fs;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use ::*;
+
+pub const SAVE_FILE_PATH: &'static str = "save.txt";
+// Bump whenever the format changes, so older saves are rejected instead of misread
+pub const SAVE_VERSION: u32 = 2;
+
+// Grace period before a resumed snake starts moving again
+pub const RESUME_DELAY_MS: i64 = 1000;
+
+pub fn has_save() -> bool {
+    Path::new(SAVE_FILE_PATH).exists()
+}
+
+pub fn delete_save() {
+    if !has_save() {
+        return;
+    }
+
+    if let Err(e) = fs::remove_file(SAVE_FILE_PATH) {
+        println!("Failed to delete save: {}", e);
+    }
+}
+
+// Everything about a run that survives quitting. Levels and settings are rebuilt from the game itself.
+pub struct SavedGame {
+    mode: GameMode,
+    level_index: usize,
+    score: u32,
+    food_collected: u32,
+    level_time: Duration,
+    rng: GameRng,
+    rewind_charges: u32,
+    boost_meter: Duration,
+    snake: Snake,
+    foods: Vec<Food>,
+    power_up: Option<PowerUp>,
+    effects: Vec<ActiveEffect>
+}
+
+impl SavedGame {
+    pub fn from_game(game: &Game) -> SavedGame {
+        SavedGame {
+            mode: game.mode,
+            level_index: game.level_index,
+            score: game.score,
+            food_collected: game.food_collected,
+            level_time: game.level_time,
+            rng: game.rng.clone(),
+            rewind_charges: game.rewind_charges,
+            boost_meter: game.boost_meter,
+            snake: game.snake.clone(),
+            foods: game.foods.clone(),
+            power_up: game.power_up.clone(),
+            effects: game.effects.clone()
+        }
+    }
+}
+
+pub fn save_game(game: &Game) {
+    let contents: String = write_save(&SavedGame::from_game(game));
+    let result = File::create(SAVE_FILE_PATH)
+        .and_then(|mut file| file.write_all(contents.as_bytes()));
+
+    if let Err(e) = result {
+        println!("Failed to save game: {}", e);
+    }
+}
+
+fn write_save(saved: &SavedGame) -> String {
+    let mut contents: String = String::new();
+
+    contents.push_str(&format!("version {}\n", SAVE_VERSION));
+    contents.push_str(&format!("mode {}\n", mode_to_str(saved.mode)));
+    contents.push_str(&format!("level {}\n", saved.level_index));
+    contents.push_str(&format!("score {}\n", saved.score));
+    contents.push_str(&format!("food_collected {}\n", saved.food_collected));
+    contents.push_str(&format!("level_time {}\n", saved.level_time.num_milliseconds()));
+    contents.push_str(&format!("rng {}\n", saved.rng.state));
+    contents.push_str(&format!("rewind_charges {}\n", saved.rewind_charges));
+    contents.push_str(&format!("boost_meter {}\n", saved.boost_meter.num_milliseconds()));
+    contents.push_str(&format!("direction {}\n", direction_to_str(&saved.snake.direction)));
+    contents.push_str(&format!("move_frequency {}\n", saved.snake.move_frequency.num_milliseconds()));
+    contents.push_str(&format!("pending_growth {}\n", saved.snake.pending_growth));
+    contents.push_str(&format!("moves {}\n", saved.snake.moves));
+
+    // Every segment has a birth, or zip would quietly drop part of the snake
+    debug_assert_eq!(saved.snake.segments.len(), saved.snake.births.len());
+    for (segment, birth) in saved.snake.segments.iter().zip(saved.snake.births.iter()) {
+        contents.push_str(&format!("segment {} {} {}\n", segment.x, segment.y, birth));
+    }
+
+    for food in &saved.foods {
+        let lifetime_ms: i64 = food.lifetime.map(|lifetime| lifetime.num_milliseconds()).unwrap_or(-1);
+        contents.push_str(&format!("food {} {} {} {}\n", food_kind_to_str(food.kind), food.position.x, food.position.y, lifetime_ms));
+    }
+
+    if let Some(ref power_up) = saved.power_up {
+        contents.push_str(&format!("power_up {} {} {} {}\n", power_up_kind_to_str(power_up.kind),
+                                   power_up.position.x, power_up.position.y, power_up.lifetime.num_milliseconds()));
+    }
+
+    for effect in &saved.effects {
+        contents.push_str(&format!("effect {} {}\n", power_up_kind_to_str(effect.kind), effect.remaining.num_milliseconds()));
+    }
+
+    contents
+}
+
+// Restores the run stored in the save file. The game is left untouched if the save can't be read.
+pub fn load_game(game: &mut Game) -> bool {
+    let mut contents: String = String::new();
+    let result = File::open(SAVE_FILE_PATH)
+        .and_then(|mut file| file.read_to_string(&mut contents));
+
+    if let Err(e) = result {
+        println!("Failed to open save: {}", e);
+        return false;
+    }
+
+    let saved: SavedGame = match parse_save(&contents) {
+        Ok(saved) => saved,
+        Err(e) => {
+            println!("Failed to load save: {}", e);
+            return false;
+        }
+    };
+
+    game.mode = saved.mode;
+    game.level_index = saved.level_index;
+
+    let level: &Level = current_level(game);
+    game.obstacles = level::build_obstacles(level);
+    game.portals = level::build_portals(level);
+    game.snake = saved.snake;
+    game.snake.last_move_time = time::now() + time::Duration::milliseconds(RESUME_DELAY_MS);
+    game.foods = saved.foods;
+    game.score = saved.score;
+    game.food_collected = saved.food_collected;
+    game.level_time = saved.level_time;
+    game.power_up = saved.power_up;
+    game.effects = saved.effects;
+    game.rng = saved.rng;
+    game.rewind_charges = saved.rewind_charges;
+    game.boost_meter = saved.boost_meter;
+    game.is_boosting = false;
+    game.history.clear();
+    game.is_rewinding = false;
+    game.death = None;
+    game.particles.clear();
+    game.state = GameState::Playing;
+
+    true
+}
+
+fn parse_save(contents: &str) -> Result<SavedGame, String> {
+    let mut saved: SavedGame = SavedGame {
+        mode: GameMode::Endless,
+        level_index: 0,
+        score: 0,
+        food_collected: 0,
+        level_time: time::Duration::zero(),
+        rng: GameRng::new(0),
+        rewind_charges: 0,
+        boost_meter: time::Duration::zero(),
+        snake: Snake::new(Coordinates::new(0, 0)),
+        foods: Vec::new(),
+        power_up: None,
+        effects: Vec::new()
+    };
+
+    saved.snake.segments.clear();
+    saved.snake.births.clear();
+
+    let mut has_version: bool = false;
+
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let key: &str = match fields.next() {
+            Some(key) => key,
+            None => { continue; }
+        };
+
+        match key {
+            "version" => {
+                let version: u32 = parse_field(fields.next())?;
+                if version != SAVE_VERSION {
+                    return Err(format!("Unsupported save version {}", version));
+                }
+
+                has_version = true;
+            },
+            "mode" => { saved.mode = mode_from_str(fields.next())?; },
+            "level" => { saved.level_index = parse_field(fields.next())?; },
+            "score" => { saved.score = parse_field(fields.next())?; },
+            "food_collected" => { saved.food_collected = parse_field(fields.next())?; },
+            "level_time" => { saved.level_time = time::Duration::milliseconds(parse_field(fields.next())?); },
+            "rng" => { saved.rng = GameRng::new(parse_field(fields.next())?); },
+            "rewind_charges" => { saved.rewind_charges = parse_field(fields.next())?; },
+            "boost_meter" => { saved.boost_meter = time::Duration::milliseconds(parse_field(fields.next())?); },
+            "direction" => { saved.snake.direction = direction_from_str(fields.next())?; },
+            "move_frequency" => { saved.snake.move_frequency = time::Duration::milliseconds(parse_field(fields.next())?); },
+            "pending_growth" => { saved.snake.pending_growth = parse_field(fields.next())?; },
+            "moves" => { saved.snake.moves = parse_field(fields.next())?; },
+            "segment" => {
+                let x: i32 = parse_field(fields.next())?;
+                let y: i32 = parse_field(fields.next())?;
+                let birth: u32 = parse_field(fields.next())?;
+
+                saved.snake.segments.push(Coordinates::new(x, y));
+                saved.snake.births.push(birth);
+            },
+            "food" => {
+                let kind: FoodKind = food_kind_from_str(fields.next())?;
+                let x: i32 = parse_field(fields.next())?;
+                let y: i32 = parse_field(fields.next())?;
+                let lifetime_ms: i64 = parse_field(fields.next())?;
+
+                let mut food: Food = Food::new(kind, Coordinates::new(x, y));
+                food.lifetime = if lifetime_ms < 0 { None } else { Some(time::Duration::milliseconds(lifetime_ms)) };
+                saved.foods.push(food);
+            },
+            "power_up" => {
+                let kind: PowerUpKind = power_up_kind_from_str(fields.next())?;
+                let x: i32 = parse_field(fields.next())?;
+                let y: i32 = parse_field(fields.next())?;
+                let lifetime_ms: i64 = parse_field(fields.next())?;
+
+                let mut power_up: PowerUp = PowerUp::new(kind, Coordinates::new(x, y));
+                power_up.lifetime = time::Duration::milliseconds(lifetime_ms);
+                saved.power_up = Some(power_up);
+            },
+            "effect" => {
+                let kind: PowerUpKind = power_up_kind_from_str(fields.next())?;
+                let remaining_ms: i64 = parse_field(fields.next())?;
+
+                let mut effect: ActiveEffect = ActiveEffect::new(kind);
+                effect.remaining = time::Duration::milliseconds(remaining_ms);
+                saved.effects.push(effect);
+            },
+            _ => { return Err(format!("Unknown save entry \"{}\"", key)); }
+        }
+    }
+
+    if !has_version {
+        return Err(String::from("Save has no version"));
+    }
+
+    if saved.snake.segments.is_empty() {
+        return Err(String::from("Save has no snake segments"));
+    }
+
+    if (saved.mode == GameMode::Campaign) && (saved.level_index >= LEVELS.len()) {
+        return Err(format!("Save references unknown level {}", saved.level_index + 1));
+    }
+
+    Ok(saved)
+}
+
+fn parse_field<T: FromStr>(field: Option<&str>) -> Result<T, String> {
+    match field {
+        Some(value) => value.parse().map_err(|_| format!("Invalid value \"{}\"", value)),
+        None => Err(String::from("Missing value"))
+    }
+}
+
+fn mode_to_str(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Endless => "endless",
+        GameMode::Campaign => "campaign"
+    }
+}
+
+fn mode_from_str(field: Option<&str>) -> Result<GameMode, String> {
+    match field {
+        Some("endless") => Ok(GameMode::Endless),
+        Some("campaign") => Ok(GameMode::Campaign),
+        _ => Err(String::from("Invalid game mode"))
+    }
+}
+
+fn direction_to_str(direction: &Direction) -> &'static str {
+    match *direction {
+        Direction::None => "none",
+        Direction::North => "north",
+        Direction::South => "south",
+        Direction::East => "east",
+        Direction::West => "west"
+    }
+}
+
+fn direction_from_str(field: Option<&str>) -> Result<Direction, String> {
+    match field {
+        Some("none") => Ok(Direction::None),
+        Some("north") => Ok(Direction::North),
+        Some("south") => Ok(Direction::South),
+        Some("east") => Ok(Direction::East),
+        Some("west") => Ok(Direction::West),
+        _ => Err(String::from("Invalid direction"))
+    }
+}
+
+fn food_kind_to_str(kind: FoodKind) -> &'static str {
+    match kind {
+        FoodKind::Normal => "normal",
+        FoodKind::Bonus => "bonus",
+        FoodKind::Poison => "poison",
+        FoodKind::Speed => "speed"
+    }
+}
+
+fn food_kind_from_str(field: Option<&str>) -> Result<FoodKind, String> {
+    match field {
+        Some("normal") => Ok(FoodKind::Normal),
+        Some("bonus") => Ok(FoodKind::Bonus),
+        Some("poison") => Ok(FoodKind::Poison),
+        Some("speed") => Ok(FoodKind::Speed),
+        _ => Err(String::from("Invalid food kind"))
+    }
+}
+
+fn power_up_kind_to_str(kind: PowerUpKind) -> &'static str {
+    match kind {
+        PowerUpKind::Ghost => "ghost",
+        PowerUpKind::SlowMotion => "slow_motion",
+        PowerUpKind::ScoreMultiplier => "score_multiplier",
+        PowerUpKind::Shrink => "shrink",
+        PowerUpKind::Magnet => "magnet",
+        PowerUpKind::Speed => "speed"
+    }
+}
+
+fn power_up_kind_from_str(field: Option<&str>) -> Result<PowerUpKind, String> {
+    match field {
+        Some("ghost") => Ok(PowerUpKind::Ghost),
+        Some("slow_motion") => Ok(PowerUpKind::SlowMotion),
+        Some("score_multiplier") => Ok(PowerUpKind::ScoreMultiplier),
+        Some("shrink") => Ok(PowerUpKind::Shrink),
+        Some("magnet") => Ok(PowerUpKind::Magnet),
+        Some("speed") => Ok(PowerUpKind::Speed),
+        _ => Err(String::from("Invalid power-up kind"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_save() -> SavedGame {
+        let mut snake: Snake = Snake::new(Coordinates::new(10, 5));
+        snake.segments.push(Coordinates::new(9, 5));
+        snake.births.push(3);
+        snake.direction = Direction::East;
+        snake.moves = 4;
+        snake.pending_growth = 2;
+
+        let mut bonus: Food = Food::new(FoodKind::Bonus, Coordinates::new(4, 7));
+        bonus.lifetime = Some(time::Duration::milliseconds(1500));
+
+        SavedGame {
+            mode: GameMode::Campaign,
+            level_index: 1,
+            score: 12,
+            food_collected: 7,
+            level_time: time::Duration::milliseconds(42000),
+            rng: GameRng::new(12345),
+            rewind_charges: 2,
+            boost_meter: time::Duration::milliseconds(800),
+            snake,
+            foods: vec![Food::new(FoodKind::Normal, Coordinates::new(3, 3)), bonus],
+            power_up: Some(PowerUp::new(PowerUpKind::Magnet, Coordinates::new(20, 9))),
+            effects: vec![ActiveEffect::new(PowerUpKind::Ghost)]
+        }
+    }
+
+    #[test]
+    fn save_round_trip() {
+        let contents: String = write_save(&test_save());
+        let parsed: SavedGame = parse_save(&contents).unwrap();
+
+        assert!(parsed.mode == GameMode::Campaign);
+        assert_eq!(parsed.level_index, 1);
+        assert_eq!(parsed.score, 12);
+        assert_eq!(parsed.rng.state, 12345);
+        assert!(parsed.snake.segments == vec![Coordinates::new(10, 5), Coordinates::new(9, 5)]);
+        assert_eq!(parsed.snake.births, vec![0, 3]);
+        assert!(parsed.snake.direction == Direction::East);
+        assert_eq!(parsed.foods.len(), 2);
+        assert_eq!(parsed.foods[1].lifetime, Some(time::Duration::milliseconds(1500)));
+        assert!(parsed.foods[0].lifetime.is_none());
+        assert_eq!(write_save(&parsed), contents);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let contents: String = write_save(&test_save());
+        let old: String = contents.replace(&format!("version {}", SAVE_VERSION), "version 1");
+
+        assert!(parse_save(&old).is_err());
+        assert!(parse_save(&contents.replace(&format!("version {}\n", SAVE_VERSION), "")).is_err());
+    }
+}
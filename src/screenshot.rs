@@ -0,0 +1,29 @@
+use std::fs;
+use std::path::Path;
+use ::*;
+
+pub const SCREENSHOT_KEY: VirtualKeyCode = VirtualKeyCode::F12;
+pub const SCREENSHOTS_PATH: &'static str = "screenshots";
+
+pub fn screenshot_path() -> String {
+    timestamped_path(SCREENSHOTS_PATH, "png")
+}
+
+// Names files after the time they were made, e.g. screenshots/schlange-20180301-142501-250.png
+pub fn timestamped_path(directory: &str, extension: &str) -> String {
+    let now: Tm = time::now();
+    let timestamp: String = time::strftime("%Y%m%d-%H%M%S", &now).unwrap();
+    format!("{}/schlange-{}-{:03}.{}", directory, timestamp, now.tm_nsec / 1000000, extension)
+}
+
+// Saves the frame that was rendered last. Has to happen before the frame is displayed.
+pub fn save_screenshot(renderer: &Renderer, path: &str) {
+    if let Some(directory) = Path::new(path).parent() {
+        let _ = fs::create_dir_all(directory);
+    }
+
+    match gfx::capture_frame(renderer).save(path) {
+        Ok(()) => { eprintln!("Saved screenshot to {}", path); },
+        Err(e) => { eprintln!("Failed to save screenshot to {}: {}", path, e); }
+    }
+}
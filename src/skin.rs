@@ -0,0 +1,64 @@
+use ::*;
+
+pub const RAINBOW_HUE_STEP: f32 = 25.0; // Degrees between neighbouring segments
+pub const RAINBOW_HUE_SPEED: f32 = 90.0; // Degrees per second
+pub const STRIPE_WIDTH: usize = 2;
+pub const AGE_FADE_MOVES: u32 = 60; // Segments this many moves old are fully faded
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Skin {
+    Solid,
+    Gradient,
+    Rainbow,
+    Striped,
+    Age
+}
+
+pub const SKINS: [Skin; 5] = [Skin::Solid, Skin::Gradient, Skin::Rainbow, Skin::Striped, Skin::Age];
+
+pub fn skin_name(skin: Skin) -> &'static str {
+    match skin {
+        Skin::Solid => "Solid",
+        Skin::Gradient => "Gradient",
+        Skin::Rainbow => "Rainbow",
+        Skin::Striped => "Striped",
+        Skin::Age => "Age"
+    }
+}
+
+// Computes the color of every segment, head first
+pub fn segment_colors(skin: Skin, theme: &Theme, snake: &Snake, time_seconds: f32) -> Vec<Color> {
+    let count: usize = snake.segments.len();
+    let faded_body: Color = gfx::lerp_color(theme.snake_body, theme.background, 0.6);
+    let mut colors: Vec<Color> = Vec::with_capacity(count);
+
+    for i in 0..count {
+        if i == 0 && skin != Skin::Rainbow {
+            colors.push(theme.snake_head);
+            continue;
+        }
+
+        let color: Color = match skin {
+            Skin::Solid => theme.snake_body,
+            Skin::Gradient => {
+                let t: f32 = i as f32 / (count.max(2) - 1) as f32;
+                gfx::lerp_color(theme.snake_body, faded_body, t)
+            },
+            Skin::Rainbow => {
+                let hue: f32 = (i as f32 * RAINBOW_HUE_STEP) + (time_seconds * RAINBOW_HUE_SPEED);
+                gfx::color_from_hsv(hue, 0.8, 1.0)
+            },
+            Skin::Striped => {
+                if ((i - 1) / STRIPE_WIDTH) % 2 == 0 { theme.snake_body } else { faded_body }
+            },
+            Skin::Age => {
+                let age: u32 = snake.moves.saturating_sub(snake.births[i]);
+                gfx::lerp_color(theme.snake_body, faded_body, age as f32 / AGE_FADE_MOVES as f32)
+            }
+        };
+
+        colors.push(color);
+    }
+
+    colors
+}
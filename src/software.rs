@@ -0,0 +1,68 @@
+use image::{RgbaImage, Rgba};
+use ::*;
+
+// Rasterizes the cell grid on the CPU by copying glyphs out of the font atlas
+pub struct SoftwareBackend {
+    pub image: RgbaImage
+}
+
+impl SoftwareBackend {
+    pub fn new(font: &Font, cols: u32, rows: u32) -> SoftwareBackend {
+        SoftwareBackend {
+            image: RgbaImage::new(cols * font.glyph_width, rows * font.glyph_height)
+        }
+    }
+}
+
+// Composites the layers in order, blending the same way the OpenGL backend does
+pub fn rasterize(backend: &mut SoftwareBackend, layers: &[CellLayer], cols: u32, font: &Font, background: Color) {
+    let background_pixel: [u8; 4] = gfx::color_to_bytes(background);
+    for pixel in backend.image.pixels_mut() {
+        *pixel = Rgba([background_pixel[0], background_pixel[1], background_pixel[2], 255]);
+    }
+
+    // Only the fallback font can get here without a usable atlas, and then there is nothing to draw
+    if font::check_atlas(font).is_err() {
+        return;
+    }
+
+    let rows: u32 = backend.image.height() / font.glyph_height;
+
+    for layer in layers {
+        for (index, cell) in layer.cells().iter().enumerate() {
+            let col: u32 = index as u32 % cols;
+            let row: u32 = index as u32 / cols;
+
+            if gfx::color_to_bytes(cell.background)[3] > 0 {
+                draw_glyph(backend, font, col, rows - 1 - row, GRAPHIC_SOLID, cell.background);
+            }
+
+            if cell.graphic != ' ' {
+                draw_glyph(backend, font, col, rows - 1 - row, cell.graphic, cell.color);
+            }
+        }
+    }
+}
+
+// Rows count from the top here, since images start at the top while the cell grid starts at the bottom
+fn draw_glyph(backend: &mut SoftwareBackend, font: &Font, col: u32, row: u32, graphic: char, color: Color) {
+    let glyph: u32 = cp437::to_cp437_or_fallback(graphic) as u32 % (font.columns * font.rows);
+    let atlas_x: u32 = (glyph % font.columns) * (font.glyph_width + font.spacing);
+    let atlas_y: u32 = (glyph / font.columns) * (font.glyph_height + font.spacing);
+    let tint: [u8; 4] = gfx::color_to_bytes(color);
+
+    for y in 0..font.glyph_height {
+        for x in 0..font.glyph_width {
+            let source: Rgba<u8> = *font.atlas.get_pixel(atlas_x + x, atlas_y + y);
+            let target: &mut Rgba<u8> = backend.image.get_pixel_mut((col * font.glyph_width) + x, (row * font.glyph_height) + y);
+
+            // Texel times vertex color, then source-alpha blending
+            let alpha: f32 = (source[3] as f32 / 255.0) * (tint[3] as f32 / 255.0);
+            for channel in 0..3 {
+                let value: f32 = (source[channel] as f32 / 255.0) * (tint[channel] as f32 / 255.0);
+                let blended: f32 = (value * alpha) + ((target[channel] as f32 / 255.0) * (1.0 - alpha));
+                target[channel] = (blended * 255.0).round() as u8;
+            }
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use std::mem;
+use glutin::VirtualKeyCode;
+use ::*;
+
+// Terminals only report key presses, repeated while a key is held. A key counts as released once it
+// has not repeated for this long, which has to outlast the usual delay before repeating starts.
+pub const KEY_RELEASE_MS: i64 = 550;
+
+// Escape sequences can arrive split across reads, e.g. over SSH. A lone ESC only counts as the Escape
+// key once nothing has followed it for this long.
+pub const ESCAPE_TIMEOUT_MS: i64 = 100;
+
+const CTRL_C: u8 = 3;
+const ESC: u8 = 0x1b;
+
+// Puts the terminal in raw mode for as long as it lives
+pub struct Terminal {
+    pub is_close_requested: bool,
+    original_termios: libc::termios,
+    key_times: HashMap<VirtualKeyCode, Tm>,
+    pending_input: Vec<u8>, // Start of an escape sequence whose end hasn't been read yet
+    pending_since: Option<Tm>
+}
+
+impl Terminal {
+    pub fn new() -> Result<Terminal, String> {
+        let original_termios: libc::termios;
+
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) == 0 {
+                return Err(String::from("Standard input is not a terminal"));
+            }
+
+            let mut termios: libc::termios = mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return Err(String::from("Failed to read the terminal settings"));
+            }
+
+            original_termios = termios;
+            libc::cfmakeraw(&mut termios);
+
+            // Reads return straight away when no key was pressed. This is set on the terminal rather than
+            // with O_NONBLOCK, which stdout would share, so that large redraws can still block.
+            termios.c_cc[libc::VMIN] = 0;
+            termios.c_cc[libc::VTIME] = 0;
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+        }
+
+        let terminal: Terminal = Terminal {
+            is_close_requested: false,
+            original_termios,
+            key_times: HashMap::new(),
+            pending_input: Vec::new(),
+            pending_since: None
+        };
+
+        // Hide the cursor and clear the screen. Dropping the terminal on failure restores its settings.
+        write_output("\x1b[?25l\x1b[2J").map_err(|e| e.to_string())?;
+
+        Ok(terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original_termios);
+        }
+
+        let _ = write_output("\x1b[0m\x1b[2J\x1b[H\x1b[?25h");
+    }
+}
+
+pub fn process_input(terminal: &mut Terminal, input_man: &mut InputMan) {
+    let mut buffer: [u8; 64] = [0; 64];
+    let mut bytes: Vec<u8> = mem::replace(&mut terminal.pending_input, Vec::new());
+    let pending_since: Option<Tm> = terminal.pending_since.take();
+
+    loop {
+        let count: isize = unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr() as *mut _, buffer.len()) };
+        if count <= 0 {
+            break;
+        }
+
+        bytes.extend_from_slice(&buffer[..count as usize]);
+    }
+
+    let now: Tm = time::now();
+    let mut i: usize = 0;
+
+    while i < bytes.len() {
+        let mut keycodes: Vec<VirtualKeyCode> = Vec::new();
+
+        match bytes[i] {
+            CTRL_C => { terminal.is_close_requested = true; },
+            ESC => match escape_sequence_length(&bytes[i..]) {
+                Some(1) => { keycodes.push(VirtualKeyCode::Escape); },
+                Some(length) => {
+                    keycodes.extend(escape_sequence_keycode(&bytes[i..(i + length)]));
+                    i += length - 1;
+                },
+                None => {
+                    let timeout: Duration = time::Duration::milliseconds(ESCAPE_TIMEOUT_MS);
+                    let is_timed_out: bool = pending_since.map_or(false, |since| now - since >= timeout);
+                    if !is_timed_out {
+                        terminal.pending_input = bytes[i..].to_vec();
+                        terminal.pending_since = Some(pending_since.unwrap_or(now));
+                        break;
+                    }
+
+                    // Nothing more came, so a lone ESC was the key itself and anything longer is dropped
+                    if i + 1 == bytes.len() {
+                        keycodes.push(VirtualKeyCode::Escape);
+                    }
+
+                    i = bytes.len();
+                }
+            },
+            0x7f | 0x08 => { keycodes.push(VirtualKeyCode::Back); },
+            b'\r' => { keycodes.push(VirtualKeyCode::Return); },
+            byte => {
+                // Terminals have no separate shift key, so capital letters count as holding it
+                if (byte as char).is_ascii_uppercase() {
+                    keycodes.push(VirtualKeyCode::LShift);
+                }
+
+                if let Some(keycode) = char_keycode((byte as char).to_ascii_lowercase()) {
+                    keycodes.push(keycode);
+                }
+            }
+        }
+
+        for keycode in keycodes {
+            input::press_key(input_man, keycode);
+            terminal.key_times.insert(keycode, now);
+        }
+
+        i += 1;
+    }
+
+    let release_time: Duration = time::Duration::milliseconds(KEY_RELEASE_MS);
+    let released: Vec<VirtualKeyCode> = terminal.key_times.iter()
+        .filter(|&(_, time)| now - *time > release_time)
+        .map(|(keycode, _)| *keycode)
+        .collect();
+
+    for keycode in released {
+        input::release_key(input_man, keycode);
+        terminal.key_times.remove(&keycode);
+    }
+}
+
+// How many bytes the escape sequence at the start of `bytes` takes up, or None if it may not have been
+// read completely yet. An ESC that doesn't start a "\x1b[" sequence is the Escape key on its own.
+fn escape_sequence_length(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() < 2 {
+        return None;
+    }
+
+    if bytes[1] != b'[' {
+        return Some(1);
+    }
+
+    let mut end: usize = 2;
+    while (end < bytes.len()) && bytes[end].is_ascii_digit() {
+        end += 1;
+    }
+
+    if end == bytes.len() {
+        return None;
+    }
+
+    Some(end + 1)
+}
+
+fn escape_sequence_keycode(sequence: &[u8]) -> Option<VirtualKeyCode> {
+    let parameters: &[u8] = &sequence[2..(sequence.len() - 1)];
+
+    match (parameters, sequence[sequence.len() - 1]) {
+        // Arrow keys steer like WASD
+        (b"", b'A') => Some(VirtualKeyCode::W),
+        (b"", b'B') => Some(VirtualKeyCode::S),
+        (b"", b'C') => Some(VirtualKeyCode::D),
+        (b"", b'D') => Some(VirtualKeyCode::A),
+        // Function keys are numbered, like "\x1b[20~" for F9 and "\x1b[24~" for F12
+        (b"20", b'~') => Some(VirtualKeyCode::F9),
+        (b"24", b'~') => Some(VirtualKeyCode::F12),
+        _ => None
+    }
+}
+
+fn char_keycode(c: char) -> Option<VirtualKeyCode> {
+    match c {
+        'a' => Some(VirtualKeyCode::A),
+        'd' => Some(VirtualKeyCode::D),
+        'f' => Some(VirtualKeyCode::F),
+        'h' => Some(VirtualKeyCode::H),
+        's' => Some(VirtualKeyCode::S),
+        't' => Some(VirtualKeyCode::T),
+        'w' => Some(VirtualKeyCode::W),
+        ' ' => Some(VirtualKeyCode::Space),
+        _ => None
+    }
+}
+
+// What a cell looks like once its layers are flattened, with alpha already blended in
+#[derive(Copy, Clone, PartialEq)]
+struct TerminalCell {
+    graphic: char,
+    foreground: [u8; 4],
+    background: [u8; 4]
+}
+
+// Draws the cell grid with ANSI escape codes, rewriting only the cells that changed since the last frame
+pub struct TerminalBackend {
+    pub output_error: Option<String>, // Set once writing to the terminal failed, e.g. after it was closed
+    previous_cells: Vec<TerminalCell>
+}
+
+impl TerminalBackend {
+    pub fn new() -> TerminalBackend {
+        TerminalBackend {
+            output_error: None,
+            previous_cells: Vec::new()
+        }
+    }
+}
+
+pub fn draw(backend: &mut TerminalBackend, layers: &[CellLayer], cols: u32, background: Color) {
+    let cells: Vec<TerminalCell> = flatten_layers(layers, background);
+    let rows: u32 = cells.len() as u32 / cols;
+    let is_full_redraw: bool = backend.previous_cells.len() != cells.len();
+    let mut output: String = String::new();
+
+    // Clearing first also wipes anything written outside the grid
+    if is_full_redraw {
+        output.push_str("\x1b[2J");
+    }
+
+    for (index, cell) in cells.iter().enumerate() {
+        if !is_full_redraw && (backend.previous_cells[index] == *cell) {
+            continue;
+        }
+
+        // The grid's first row is at the bottom, the terminal's at the top. Both count from 1 here.
+        let col: u32 = (index as u32 % cols) + 1;
+        let row: u32 = rows - (index as u32 / cols);
+        let foreground: [u8; 4] = cell.foreground;
+        let background: [u8; 4] = cell.background;
+
+        output.push_str(&format!("\x1b[{};{}H\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m{}", row, col,
+                                 foreground[0], foreground[1], foreground[2],
+                                 background[0], background[1], background[2], cell.graphic));
+    }
+
+    if !output.is_empty() {
+        output.push_str("\x1b[0m");
+        if let Err(e) = write_output(&output) {
+            backend.output_error = Some(e.to_string());
+        }
+    }
+
+    backend.previous_cells = cells;
+}
+
+// Repaints every cell on the next frame, e.g. after other output was written over the grid
+pub fn force_redraw(backend: &mut TerminalBackend) {
+    backend.previous_cells.clear();
+}
+
+// Mirrors the blending of the other backends: backgrounds cover whatever is beneath them and glyphs
+// are drawn over the background
+fn flatten_layers(layers: &[CellLayer], background: Color) -> Vec<TerminalCell> {
+    let cell_count: usize = layers[0].cells().len();
+    let mut cells: Vec<TerminalCell> = Vec::with_capacity(cell_count);
+
+    for index in 0..cell_count {
+        let mut graphic: char = ' ';
+        let mut foreground: Color = background;
+        let mut cell_background: Color = background;
+
+        for layer in layers {
+            let sprite: Sprite = layer.cells()[index];
+
+            let background_alpha: f32 = gfx::color_to_bytes(sprite.background)[3] as f32 / 255.0;
+            if background_alpha > 0.0 {
+                cell_background = gfx::lerp_color(cell_background, gfx::color_with_alpha(sprite.background, 1.0), background_alpha);
+                if background_alpha >= 1.0 {
+                    graphic = ' ';
+                }
+            }
+
+            if sprite.graphic != ' ' {
+                let alpha: f32 = gfx::color_to_bytes(sprite.color)[3] as f32 / 255.0;
+                graphic = sprite.graphic;
+                foreground = gfx::lerp_color(cell_background, gfx::color_with_alpha(sprite.color, 1.0), alpha);
+            }
+        }
+
+        cells.push(TerminalCell {
+            graphic: printable_char(graphic),
+            foreground: gfx::color_to_bytes(foreground),
+            background: gfx::color_to_bytes(cell_background)
+        });
+    }
+
+    cells
+}
+
+// Draws characters the way the font atlas would, so anything without a glyph shows the fallback
+fn printable_char(c: char) -> char {
+    match cp437::to_cp437_or_fallback(c) {
+        0 | 0xff => ' ',
+        glyph => cp437::CP437_CHARS[glyph as usize]
+    }
+}
+
+fn write_output(output: &str) -> io::Result<()> {
+    let stdout = std::io::stdout();
+    let mut handle = stdout.lock();
+    handle.write_all(output.as_bytes())?;
+    handle.flush()
+}
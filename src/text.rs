@@ -0,0 +1,166 @@
+use ::*;
+
+pub const ELLIPSIS: &'static str = "...";
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right
+}
+
+// Cuts a string down to the given width, ending it with an ellipsis if anything was cut
+pub fn truncate(string: &str, width: u32) -> String {
+    let width: usize = width as usize;
+    if string.chars().count() <= width {
+        return String::from(string);
+    }
+
+    if width <= ELLIPSIS.len() {
+        return string.chars().take(width).collect();
+    }
+
+    let mut truncated: String = string.chars().take(width - ELLIPSIS.len()).collect();
+    truncated.push_str(ELLIPSIS);
+    truncated
+}
+
+// Breaks a string into lines at word boundaries. Newlines start a new line and words that are
+// too long for a line of their own are split.
+pub fn wrap_text(string: &str, width: u32) -> Vec<String> {
+    let width: usize = width as usize;
+    let mut lines: Vec<String> = Vec::new();
+    if width == 0 {
+        return lines;
+    }
+
+    for paragraph in string.split('\n') {
+        let mut line: String = String::new();
+        let mut line_length: usize = 0;
+
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+
+            while word.len() > width {
+                if line_length > 0 {
+                    lines.push(line);
+                    line = String::new();
+                    line_length = 0;
+                }
+
+                let rest: Vec<char> = word.split_off(width);
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+
+            if word.is_empty() {
+                continue;
+            }
+
+            if (line_length > 0) && (line_length + 1 + word.len() > width) {
+                lines.push(line);
+                line = String::new();
+                line_length = 0;
+            }
+
+            if line_length > 0 {
+                line.push(' ');
+                line_length += 1;
+            }
+
+            line_length += word.len();
+            line.extend(word);
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+// Draws a single line within a field of the given width, clipping it with an ellipsis if it does not fit
+pub fn draw_aligned(renderer: &mut Renderer, x: i32, y: i32, width: u32, string: &str, align: Align) {
+    let text: String = truncate(string, width);
+    let free_space: i32 = width as i32 - text.chars().count() as i32;
+
+    let offset: i32 = match align {
+        Align::Left => 0,
+        Align::Center => free_space / 2,
+        Align::Right => free_space
+    };
+
+    gfx::draw_string(renderer, x + offset, y, &text);
+}
+
+// Draws wrapped text into a rectangle, top line first. Lines that do not fit are dropped and the
+// last visible line ends with an ellipsis. Returns the number of lines drawn.
+pub fn draw_text(renderer: &mut Renderer, x: i32, y: i32, width: u32, height: u32, string: &str, align: Align) -> u32 {
+    let mut lines: Vec<String> = wrap_text(string, width);
+
+    if lines.len() > height as usize {
+        lines.truncate(height as usize);
+        if let Some(last) = lines.last_mut() {
+            // Make room for the ellipsis here, so draw_aligned doesn't cut the line a second time
+            let kept: usize = (width as usize).saturating_sub(ELLIPSIS.len()).min(last.chars().count());
+            *last = last.chars().take(kept).collect();
+            last.push_str(ELLIPSIS);
+        }
+    }
+
+    let top: i32 = y + height as i32 - 1;
+    for (i, line) in lines.iter().enumerate() {
+        draw_aligned(renderer, x, top - i as i32, width, line, align);
+    }
+
+    lines.len() as u32
+}
+
+// Draws wrapped text in a framed box that hides whatever is behind it. Since the height depends on
+// the text, the box hangs down from `top`, unlike draw_box which grows upwards. Returns the box size.
+pub fn draw_text_box(renderer: &mut Renderer, x: i32, top: i32, max_width: u32, string: &str, align: Align) -> (u32, u32) {
+    // Border and one column of padding on either side
+    let lines: Vec<String> = wrap_text(string, max_width.saturating_sub(4));
+    let text_width: u32 = lines.iter().map(|line| line.chars().count() as u32).max().unwrap_or(0);
+    let width: u32 = text_width + 4;
+    let height: u32 = lines.len() as u32 + 2;
+    let bottom: i32 = top - height as i32 + 1;
+
+    for row in bottom..(top + 1) {
+        for col in x..(x + width as i32) {
+            gfx::draw_cell(renderer, col, row, SPRITE_NONE);
+        }
+    }
+
+    gfx::draw_box(renderer, x, bottom, width, height);
+    for (i, line) in lines.iter().enumerate() {
+        draw_aligned(renderer, x + 2, top - 1 - i as i32, text_width, line, align);
+    }
+
+    let background: Color = renderer.background_color;
+    gfx::draw_background(renderer, x, bottom, width, height, background);
+
+    (width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_edge_cases() {
+        assert_eq!(truncate("snake", 0), "");
+        assert_eq!(truncate("snake", 5), "snake");
+        assert_eq!(truncate("snake", 2), "sn");
+        assert_eq!(truncate("rattlesnake", 8), "rattl...");
+        assert_eq!(truncate("schlängeln", 9), "schlän...");
+    }
+
+    #[test]
+    fn wrap_text_edge_cases() {
+        assert!(wrap_text("any text", 0).is_empty());
+        assert_eq!(wrap_text("", 10), vec![""]);
+        assert_eq!(wrap_text("eat the food", 7), vec!["eat the", "food"]);
+        assert_eq!(wrap_text("a rattlesnake", 5), vec!["a", "rattl", "esnak", "e"]);
+        assert_eq!(wrap_text("one\n\ntwo three", 20), vec!["one", "", "two three"]);
+    }
+}
@@ -0,0 +1,90 @@
+use ::*;
+
+pub const THEMES_PATH: &'static str = "themes";
+pub const THEME_FILES: [&'static str; 5] = [
+    "classic.palette",
+    "amber.palette",
+    "green_phosphor.palette",
+    "high_contrast.palette",
+    "colorblind.palette"
+];
+
+// Maps the semantic roles used when rendering to actual colors
+pub struct Theme {
+    pub name: String,
+    pub snake_head: Color,
+    pub snake_body: Color,
+    pub food: Color,
+    pub wall: Color,
+    pub text: Color,
+    pub border: Color,
+    pub background: Color
+}
+
+impl Theme {
+    pub fn classic() -> Theme {
+        Theme {
+            name: String::from("Classic"),
+            snake_head: COLOR_WHITE,
+            snake_body: COLOR_WHITE,
+            food: COLOR_GREEN,
+            wall: COLOR_GRAY,
+            text: COLOR_WHITE,
+            border: COLOR_WHITE,
+            background: COLOR_BLACK
+        }
+    }
+}
+
+// Loads every palette that can be read. Falls back to the classic colors if none can.
+pub fn load_themes() -> Vec<Theme> {
+    let mut themes: Vec<Theme> = Vec::new();
+
+    for file_name in THEME_FILES.iter() {
+        let path: String = format!("{}/{}", THEMES_PATH, file_name);
+        match load_theme(&path) {
+            Ok(theme) => { themes.push(theme); },
+            Err(e) => { println!("Failed to load theme {}: {}", path, e); }
+        }
+    }
+
+    if themes.is_empty() {
+        themes.push(Theme::classic());
+    }
+
+    themes
+}
+
+// Palettes give each role an RRGGBB color. Roles that are left out keep their classic color.
+fn load_theme(path: &str) -> Result<Theme, String> {
+    let contents: String = config::read_file(path)?;
+    let mut theme: Theme = Theme::classic();
+
+    for (key, value) in config::parse_key_values(&contents)? {
+        match key {
+            "name" => { theme.name = String::from(value); },
+            "snake_head" => { theme.snake_head = parse_color(value)?; },
+            "snake_body" => { theme.snake_body = parse_color(value)?; },
+            "food" => { theme.food = parse_color(value)?; },
+            "wall" => { theme.wall = parse_color(value)?; },
+            "text" => { theme.text = parse_color(value)?; },
+            "border" => { theme.border = parse_color(value)?; },
+            "background" => { theme.background = parse_color(value)?; },
+            _ => { return Err(format!("Unknown role \"{}\"", key)); }
+        }
+    }
+
+    Ok(theme)
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    let hex: &str = value.trim_start_matches('#');
+    if (hex.len() != 6) || !hex.is_ascii() {
+        return Err(format!("Invalid color \"{}\"", value));
+    }
+
+    let channel = |start: usize| u8::from_str_radix(&hex[start..(start + 2)], 16)
+        .map_err(|_| format!("Invalid color \"{}\"", value));
+
+    Ok(Color::new(channel(0)?, channel(2)?, channel(4)?, 255))
+}